@@ -0,0 +1,111 @@
+#![feature(test)]
+
+extern crate bonzai;
+extern crate test;
+
+use bonzai::collections::BonzaiMap;
+use std::collections::BTreeMap;
+use test::{Bencher, black_box};
+
+const NUM_KEYS: usize = 10_000;
+
+/// Deterministic pseudo-random keys from an xorshift generator.
+fn random_keys() -> Vec<u64> {
+    let mut state: u64 = 0x2545F4914F6CDD1D;
+    (0..NUM_KEYS)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        })
+        .collect()
+}
+
+#[bench]
+fn bonzai_insert(b: &mut Bencher) {
+    let keys = random_keys();
+    b.iter(|| {
+        let mut map = BonzaiMap::new();
+        for &key in &keys {
+            map.insert(key, key);
+        }
+        map
+    });
+}
+
+#[bench]
+fn btree_insert(b: &mut Bencher) {
+    let keys = random_keys();
+    b.iter(|| {
+        let mut map = BTreeMap::new();
+        for &key in &keys {
+            map.insert(key, key);
+        }
+        map
+    });
+}
+
+#[bench]
+fn bonzai_get(b: &mut Bencher) {
+    let keys = random_keys();
+    let map: BonzaiMap<u64, u64> = keys.iter().map(|&key| (key, key)).collect();
+    b.iter(|| {
+        for key in &keys {
+            black_box(map.get(key));
+        }
+    });
+}
+
+#[bench]
+fn btree_get(b: &mut Bencher) {
+    let keys = random_keys();
+    let map: BTreeMap<u64, u64> = keys.iter().map(|&key| (key, key)).collect();
+    b.iter(|| {
+        for key in &keys {
+            black_box(map.get(key));
+        }
+    });
+}
+
+#[bench]
+fn bonzai_insert_remove(b: &mut Bencher) {
+    let keys = random_keys();
+    b.iter(|| {
+        let mut map = BonzaiMap::new();
+        for &key in &keys {
+            map.insert(key, key);
+        }
+        for key in &keys {
+            black_box(map.remove(key));
+        }
+    });
+}
+
+#[bench]
+fn btree_insert_remove(b: &mut Bencher) {
+    let keys = random_keys();
+    b.iter(|| {
+        let mut map = BTreeMap::new();
+        for &key in &keys {
+            map.insert(key, key);
+        }
+        for key in &keys {
+            black_box(map.remove(key));
+        }
+    });
+}
+
+#[bench]
+fn bonzai_iter(b: &mut Bencher) {
+    let keys = random_keys();
+    let map: BonzaiMap<u64, u64> = keys.iter().map(|&key| (key, key)).collect();
+    b.iter(|| map.values().fold(0u64, |sum, &value| sum.wrapping_add(value)));
+}
+
+#[bench]
+fn btree_iter(b: &mut Bencher) {
+    let keys = random_keys();
+    let map: BTreeMap<u64, u64> = keys.iter().map(|&key| (key, key)).collect();
+    b.iter(|| map.values().fold(0u64, |sum, &value| sum.wrapping_add(value)));
+}
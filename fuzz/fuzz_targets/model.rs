@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use bonzai::model::{self, Op};
+
+// the first byte chooses whether to cache shapes, and the rest are decoded into operations
+fuzz_target!(|data: &[u8]| {
+    if let Some((&flags, ops)) = data.split_first() {
+        model::check(&Op::decode(ops), flags & 1 == 1);
+    }
+});
@@ -0,0 +1,83 @@
+//! Addressing nodes, either by their index in the node vec, or by their path from the root.
+
+use {Tree, TreeOperation, Node, ParentId, ChildId, NodeIndex, NodeReadGuard, NodeWriteGuard, GetElemMut};
+
+use core::array::FixedSizeArray;
+use std::marker::PhantomData;
+
+/// A way of addressing a node of a tree, for `Tree::get`, `TreeOperation::get` and
+/// `TreeOperation::get_mut`. This is implemented for `NodeIndex` and `&BranchPath`.
+pub trait NodeAddress {
+    /// Resolve this address to the index of a live node which is attached to the tree's root,
+    /// if there is one.
+    fn resolve<T, C: FixedSizeArray<ChildId>>(self, tree: &Tree<T, C>) -> Option<NodeIndex>;
+}
+
+impl NodeAddress for NodeIndex {
+    /// A node index resolves if it refers to a present node whose ancestors lead up to the root,
+    /// rather than to a detached subtree or to garbage. This is an O(D) operation, where D is the
+    /// depth of the node. Node indices are only meaningful until the next garbage collection.
+    fn resolve<T, C: FixedSizeArray<ChildId>>(self, tree: &Tree<T, C>) -> Option<NodeIndex> {
+        unsafe {
+            let nodes = &*tree.nodes.get();
+            let mut curr = self.index;
+            loop {
+                if curr >= nodes.len() {
+                    return None;
+                }
+                match &*nodes[curr].get() {
+                    &Node::Present {
+                        ref parent,
+                        ..
+                    } => match parent.get() {
+                        ParentId::Some {
+                            parent_index,
+                            ..
+                        } => curr = parent_index,
+                        ParentId::Root => return Some(self),
+                        ParentId::Detached | ParentId::Garbage => return None,
+                    },
+                    &Node::Garbage { .. } => return None,
+                };
+            }
+        }
+    }
+}
+
+impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
+    /// Read the node at an address, if it exists.
+    pub fn get<'tree, A: NodeAddress>(&'tree self, address: A) -> Option<NodeReadGuard<'tree, T, C>> {
+        address.resolve(self)
+            .map(|node| unsafe {
+                NodeReadGuard::new(self, node.index)
+            })
+    }
+}
+
+impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
+    /// Read the node at an address, if it exists and is attached to the root. This allows
+    /// going from a `NodeIndex`, such as the key of a side table, back to its node.
+    pub fn get<'s, A: NodeAddress>(&'s self, address: A) -> Option<NodeReadGuard<'s, T, C>> {
+        self.tree.get(address)
+    }
+
+    /// Write to the node at an address, if it exists and is attached to the root.
+    pub fn get_mut<'s, A: NodeAddress>(&'s mut self, address: A) -> Option<NodeWriteGuard<'s, 's, 'tree, T, C>> {
+        let self_immutable: &Self = self;
+
+        address.resolve(self_immutable.tree)
+            .map(|node| NodeWriteGuard {
+                op: self_immutable,
+                index: node.index,
+
+                p1: PhantomData,
+            })
+    }
+}
+
+impl<'tree, T, C: FixedSizeArray<ChildId>> GetElemMut<T> for TreeOperation<'tree, T, C> {
+    fn get_elem_mut(&mut self, index: NodeIndex) -> Option<&mut T> {
+        self.get_mut(index)
+            .map(|node| node.into_split().0)
+    }
+}
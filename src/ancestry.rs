@@ -0,0 +1,291 @@
+//! Queries about the ancestry of nodes, and the paths between them.
+
+use {Tree, Node, ParentId, ChildId, NodeIndex};
+
+use core::array::FixedSizeArray;
+use std::vec::Vec;
+
+/// Whether the index refers to a present node of the tree.
+unsafe fn is_present<T, C: FixedSizeArray<ChildId>>(tree: &Tree<T, C>, index: usize) -> bool {
+    let nodes = &*tree.nodes.get();
+    index < nodes.len() && match &*nodes[index].get() {
+        &Node::Present { .. } => true,
+        &Node::Garbage { .. } => false,
+    }
+}
+
+/// The index of a present node's parent, or none if it is the root.
+unsafe fn parent_of<T, C: FixedSizeArray<ChildId>>(tree: &Tree<T, C>, index: usize) -> Option<usize> {
+    match &*(&*tree.nodes.get())[index].get() {
+        &Node::Present {
+            ref parent,
+            ..
+        } => match parent.get() {
+            ParentId::Some {
+                parent_index,
+                ..
+            } => Some(parent_index),
+            ParentId::Root | ParentId::Detached => None,
+            ParentId::Garbage => unreachable!("encountered garbage parent outside of GC"),
+        },
+        &Node::Garbage { .. } => unreachable!("parent of garbage node"),
+    }
+}
+
+/// The depth of a present node, from the shape cache if the tree has one.
+unsafe fn depth_of<T, C: FixedSizeArray<ChildId>>(tree: &Tree<T, C>, index: usize) -> usize {
+    if tree.shape_cache {
+        (&*tree.shapes.get())[index].depth
+    } else {
+        let mut depth = 0;
+        let mut curr = index;
+        while let Some(parent_index) = parent_of(tree, curr) {
+            depth += 1;
+            curr = parent_index;
+        }
+        depth
+    }
+}
+
+/// Given the two halves of a path which meet at their common ancestor, each listed upwards,
+/// join them into the path from the start of `up` to the start of `down`.
+fn join_path(mut up: Vec<usize>, down: Vec<usize>) -> Vec<NodeIndex> {
+    up.extend(down.into_iter().rev().skip(1));
+    up.into_iter()
+        .map(|index| NodeIndex {
+            index
+        })
+        .collect()
+}
+
+impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
+    /// The number of ancestors of a node, or none if the index does not refer to a node in this
+    /// tree.
+    ///
+    /// This is O(1) if the tree caches its shape, and O(D) otherwise, where D is the depth.
+    pub fn depth(&self, node: NodeIndex) -> Option<usize> {
+        unsafe {
+            if is_present(self, node.index) {
+                Some(depth_of(self, node.index))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Whether `ancestor` is an ancestor of `node`, or is `node` itself. This is O(D), where D is
+    /// the depth of `node`.
+    pub fn is_ancestor(&self, ancestor: NodeIndex, node: NodeIndex) -> bool {
+        unsafe {
+            if !is_present(self, ancestor.index) || !is_present(self, node.index) {
+                return false;
+            }
+            let mut curr = Some(node.index);
+            while let Some(index) = curr {
+                if index == ancestor.index {
+                    return true;
+                }
+                curr = parent_of(self, index);
+            }
+            false
+        }
+    }
+
+    /// The lowest common ancestor of two nodes, which may be one of the nodes themselves, or
+    /// none if either index does not refer to a node in this tree.
+    ///
+    /// This is O(D), where D is the greater depth of the two nodes. For repeated queries on a
+    /// tree which is not being modified, `ancestor_index` provides O(log N) queries.
+    pub fn lca(&self, a: NodeIndex, b: NodeIndex) -> Option<NodeIndex> {
+        self.paths_to_lca(a, b)
+            .map(|(a_path, _)| NodeIndex {
+                index: *a_path.last().unwrap()
+            })
+    }
+
+    /// The sequence of nodes along the path from `a` to `b`, including both, going up to their
+    /// lowest common ancestor and then down. None if either index does not refer to a node in
+    /// this tree.
+    pub fn path_between(&self, a: NodeIndex, b: NodeIndex) -> Option<Vec<NodeIndex>> {
+        self.paths_to_lca(a, b)
+            .map(|(a_path, b_path)| join_path(a_path, b_path))
+    }
+
+    /// Build an index over the current shape of the tree, which answers ancestry queries in
+    /// O(log N) time. This is an O(N log N) operation.
+    ///
+    /// The index borrows the tree, so the tree cannot be modified while the index exists.
+    pub fn ancestor_index<'tree>(&'tree self) -> AncestorIndex<'tree, T, C> {
+        AncestorIndex::new(self)
+    }
+
+    /// The paths from each node up to their lowest common ancestor, inclusive.
+    fn paths_to_lca(&self, a: NodeIndex, b: NodeIndex) -> Option<(Vec<usize>, Vec<usize>)> {
+        unsafe {
+            if !is_present(self, a.index) || !is_present(self, b.index) {
+                return None;
+            }
+            let mut a_depth = depth_of(self, a.index);
+            let mut b_depth = depth_of(self, b.index);
+            let mut a_path = vec![a.index];
+            let mut b_path = vec![b.index];
+            while a_depth > b_depth {
+                a_path.push(parent_of(self, *a_path.last().unwrap()).unwrap());
+                a_depth -= 1;
+            }
+            while b_depth > a_depth {
+                b_path.push(parent_of(self, *b_path.last().unwrap()).unwrap());
+                b_depth -= 1;
+            }
+            while a_path.last() != b_path.last() {
+                a_path.push(parent_of(self, *a_path.last().unwrap()).unwrap());
+                b_path.push(parent_of(self, *b_path.last().unwrap()).unwrap());
+            }
+            Some((a_path, b_path))
+        }
+    }
+}
+
+/// A precomputed binary lifting index over a tree, which answers ancestry queries in O(log N)
+/// time. Created by `Tree::ancestor_index`.
+pub struct AncestorIndex<'tree, T: 'tree, C: FixedSizeArray<ChildId> + 'tree> {
+    tree: &'tree Tree<T, C>,
+    /// The depth of each node.
+    depths: Vec<usize>,
+    /// The pre-order position of each node, and the position after its last descendant.
+    spans: Vec<(usize, usize)>,
+    /// The 2^k-th ancestor of each node for each level k, saturating at the root.
+    jumps: Vec<Vec<usize>>,
+}
+impl<'tree, T, C: FixedSizeArray<ChildId>> AncestorIndex<'tree, T, C> {
+    fn new(tree: &'tree Tree<T, C>) -> Self {
+        unsafe {
+            let nodes = &*tree.nodes.get();
+            let len = nodes.len();
+            let mut depths = vec![0; len];
+            let mut spans = vec![(0, 0); len];
+            let mut parents: Vec<usize> = (0..len).collect();
+            let mut max_depth = 0;
+
+            // pre-order traversal, where each node is visited again once its subtree is done
+            let mut counter = 0;
+            let mut stack: Vec<(usize, bool)> = tree.root.get()
+                .map(|root_index| (root_index, false))
+                .into_iter()
+                .collect();
+            while let Some((index, done)) = stack.pop() {
+                if done {
+                    spans[index].1 = counter;
+                    continue;
+                }
+                spans[index].0 = counter;
+                counter += 1;
+                stack.push((index, true));
+                if let &Node::Present {
+                    ref children,
+                    ..
+                } = &*nodes[index].get() {
+                    for child_index in (&*children.get()).as_slice().iter().rev()
+                        .filter_map(|child_id| child_id.index) {
+                        depths[child_index] = depths[index] + 1;
+                        parents[child_index] = index;
+                        max_depth = max_depth.max(depths[child_index]);
+                        stack.push((child_index, false));
+                    }
+                }
+            }
+
+            let mut jumps = vec![parents];
+            let mut span = 1;
+            while span < max_depth {
+                let next: Vec<usize> = {
+                    let prev = jumps.last().unwrap();
+                    prev.iter().map(|&ancestor| prev[ancestor]).collect()
+                };
+                jumps.push(next);
+                span *= 2;
+            }
+
+            AncestorIndex {
+                tree,
+                depths,
+                spans,
+                jumps,
+            }
+        }
+    }
+
+    /// The tree which this index was built over.
+    pub fn tree(&self) -> &'tree Tree<T, C> {
+        self.tree
+    }
+
+    /// The number of ancestors of a node, or none if the index does not refer to a node in the
+    /// tree.
+    pub fn depth(&self, node: NodeIndex) -> Option<usize> {
+        if self.is_present(node.index) {
+            Some(self.depths[node.index])
+        } else {
+            None
+        }
+    }
+
+    /// Whether `ancestor` is an ancestor of `node`, or is `node` itself. This is O(1).
+    pub fn is_ancestor(&self, ancestor: NodeIndex, node: NodeIndex) -> bool {
+        self.is_present(ancestor.index) && self.is_present(node.index) && {
+            let (start, end) = self.spans[ancestor.index];
+            let (position, _) = self.spans[node.index];
+            start <= position && position < end
+        }
+    }
+
+    /// The lowest common ancestor of two nodes, which may be one of the nodes themselves, or
+    /// none if either index does not refer to a node in the tree.
+    pub fn lca(&self, a: NodeIndex, b: NodeIndex) -> Option<NodeIndex> {
+        if !self.is_present(a.index) || !self.is_present(b.index) {
+            return None;
+        }
+        if self.is_ancestor(a, b) {
+            return Some(a);
+        }
+        if self.is_ancestor(b, a) {
+            return Some(b);
+        }
+        // climb from a to the highest ancestor which is not also an ancestor of b
+        let mut curr = a.index;
+        for level in self.jumps.iter().rev() {
+            let ancestor = NodeIndex {
+                index: level[curr]
+            };
+            if !self.is_ancestor(ancestor, b) {
+                curr = ancestor.index;
+            }
+        }
+        Some(NodeIndex {
+            index: self.jumps[0][curr]
+        })
+    }
+
+    /// The sequence of nodes along the path from `a` to `b`, including both, going up to their
+    /// lowest common ancestor and then down. None if either index does not refer to a node in
+    /// the tree.
+    pub fn path_between(&self, a: NodeIndex, b: NodeIndex) -> Option<Vec<NodeIndex>> {
+        self.lca(a, b)
+            .map(|lca| {
+                let climb = |from: usize| {
+                    let mut path = vec![from];
+                    while *path.last().unwrap() != lca.index {
+                        let parent_index = self.jumps[0][*path.last().unwrap()];
+                        path.push(parent_index);
+                    }
+                    path
+                };
+                join_path(climb(a.index), climb(b.index))
+            })
+    }
+
+    /// Whether the index refers to a node which was reached when building the index.
+    fn is_present(&self, index: usize) -> bool {
+        index < self.spans.len() && self.spans[index].1 > self.spans[index].0
+    }
+}
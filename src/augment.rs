@@ -0,0 +1,163 @@
+//! Maintenance of subtree aggregates.
+
+use {Tree, Node, ParentId, ChildId, NodeReadGuard};
+
+use core::array::FixedSizeArray;
+use std::ops::{Deref, DerefMut};
+use std::fmt::{Debug, Formatter};
+use std::fmt;
+use std::vec::Vec;
+
+/// Element types for which an aggregate value can be computed over every subtree, such as
+/// subtree sizes, sums, or bounding boxes.
+pub trait Augment {
+    /// The aggregate value of a subtree.
+    type Agg;
+
+    /// Compute the aggregate of the subtree rooted at a node, given that node's element and the
+    /// aggregates of its children. `children` has an entry for every branch.
+    fn combine(&self, children: &[Option<&Self::Agg>]) -> Self::Agg;
+
+    /// Compute the aggregate of a subtree which consists of only this element, for a newly
+    /// created element, before it is known how many branches its node has.
+    fn leaf(&self) -> Self::Agg;
+}
+
+/// An element of an augmented tree, which stores the aggregate of its subtree alongside the
+/// inner element. Dereferences to the inner element.
+///
+/// The aggregates are only maintained for trees created with `Tree::new_augmented`. They are
+/// brought up to date whenever a `TreeOperation` is dropped, or when `update_aggregates` is called.
+/// Elements mutated through `Tree::get_elem_mut`, outside of any operation, leave the aggregates
+/// stale until then.
+pub struct Augmented<E: Augment> {
+    elem: E,
+    agg: E::Agg,
+}
+impl<E: Augment> Augmented<E> {
+    /// Wrap an element, with the aggregate of a leaf.
+    pub fn new(elem: E) -> Self {
+        let agg = elem.leaf();
+        Augmented {
+            elem,
+            agg,
+        }
+    }
+
+    /// The aggregate of the subtree rooted at this element's node.
+    pub fn aggregate(&self) -> &E::Agg {
+        &self.agg
+    }
+
+    /// Unwrap the inner element.
+    pub fn into_inner(self) -> E {
+        self.elem
+    }
+}
+impl<E: Augment> Deref for Augmented<E> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        &self.elem
+    }
+}
+impl<E: Augment> DerefMut for Augmented<E> {
+    fn deref_mut(&mut self) -> &mut E {
+        &mut self.elem
+    }
+}
+impl<E: Augment + Debug> Debug for Augmented<E> where E::Agg: Debug {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("Augmented")
+            .field("elem", &self.elem)
+            .field("agg", &self.agg)
+            .finish()
+    }
+}
+
+impl<E: Augment, C: FixedSizeArray<ChildId>> Tree<Augmented<E>, C> {
+    /// Create a new, empty tree, which maintains the aggregate of every subtree.
+    pub fn new_augmented() -> Self {
+        let mut tree = Tree::new();
+        tree.augment = Some(update::<E, C>);
+        tree
+    }
+}
+
+impl<'tree, E: Augment, C: FixedSizeArray<ChildId>> NodeReadGuard<'tree, Augmented<E>, C> {
+    /// The aggregate of the subtree rooted at this node.
+    ///
+    /// Within a `TreeOperation`, this does not reflect changes made during that operation until
+    /// `TreeOperation::update_aggregates` is called.
+    pub fn aggregate(&self) -> &'tree E::Agg {
+        &self.elem.agg
+    }
+}
+
+/// Recompute the aggregates of every dirty node and its ancestors, children before parents.
+fn update<E: Augment, C: FixedSizeArray<ChildId>>(tree: &mut Tree<Augmented<E>, C>) {
+    unsafe {
+        let dirty = &mut *tree.dirty.get();
+        if dirty.is_empty() {
+            return;
+        }
+        let nodes = &*tree.nodes.get();
+
+        // gather the dirty nodes and their ancestors, paired with their depth
+        let mut stale: Vec<(usize, usize)> = Vec::new();
+        let mut path: Vec<usize> = Vec::new();
+        'dirty: for index in dirty.drain(..) {
+            path.clear();
+            let mut curr = index;
+            loop {
+                match &*nodes[curr].get() {
+                    &Node::Present {
+                        ref parent,
+                        ..
+                    } => {
+                        path.push(curr);
+                        match parent.get() {
+                            ParentId::Some {
+                                parent_index,
+                                ..
+                            } => curr = parent_index,
+                            ParentId::Root | ParentId::Detached => break,
+                            ParentId::Garbage => continue 'dirty,
+                        };
+                    },
+                    // the dirty node has since become garbage
+                    &Node::Garbage { .. } => continue 'dirty,
+                };
+            }
+            let len = path.len();
+            stale.extend(path.iter().enumerate().map(|(i, &index)| (len - 1 - i, index)));
+        }
+
+        // deepest first, so that children are always recomputed before their parents
+        stale.sort_unstable_by(|a, b| b.cmp(a));
+        stale.dedup();
+
+        for &(_, index) in &stale {
+            if let &Node::Present {
+                ref elem,
+                ref children,
+                ..
+            } = &*nodes[index].get() {
+                let agg = {
+                    let child_aggs: Vec<Option<&E::Agg>> = (&*children.get()).as_slice().iter()
+                        .map(|child_id| child_id.index
+                            .map(|child_index| match &*nodes[child_index].get() {
+                                &Node::Present {
+                                    ref elem,
+                                    ..
+                                } => &(&*elem.get()).agg,
+                                &Node::Garbage { .. } => unreachable!("node child is garbage"),
+                            }))
+                        .collect();
+                    (&*elem.get()).elem.combine(&child_aggs)
+                };
+                (&mut *elem.get()).agg = agg;
+            }
+        }
+    }
+}
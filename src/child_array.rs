@@ -0,0 +1,87 @@
+//! Splitting a node's children into a fixed-size array of write guards.
+
+use {ChildId, NodeWriteGuard, ChildWriteGuard};
+
+use core::array::FixedSizeArray;
+
+/// Children arrays for which an array of the same length, of any type, can be built. This is
+/// implemented for `[ChildId; N]` for every `N` up to 32, and allows the children of a node to be
+/// split into an array which can be destructured, such as `let [left, right] = ...`.
+pub trait ChildArray<X>: FixedSizeArray<ChildId> {
+    /// An array of `X` with one entry for each branch.
+    type Array;
+
+    /// Build the array by calling a function with each branch index, in order.
+    fn from_fn<F: FnMut(usize) -> X>(f: F) -> Self::Array;
+}
+
+macro_rules! child_arrays {
+    ($len:expr; $($branch:expr),*) => {
+        impl<X> ChildArray<X> for [ChildId; $len] {
+            type Array = [X; $len];
+
+            #[allow(unused_mut, unused_variables)]
+            fn from_fn<F: FnMut(usize) -> X>(mut f: F) -> [X; $len] {
+                [$(f($branch)),*]
+            }
+        }
+    };
+}
+
+child_arrays!(0;);
+child_arrays!(1; 0);
+child_arrays!(2; 0, 1);
+child_arrays!(3; 0, 1, 2);
+child_arrays!(4; 0, 1, 2, 3);
+child_arrays!(5; 0, 1, 2, 3, 4);
+child_arrays!(6; 0, 1, 2, 3, 4, 5);
+child_arrays!(7; 0, 1, 2, 3, 4, 5, 6);
+child_arrays!(8; 0, 1, 2, 3, 4, 5, 6, 7);
+child_arrays!(9; 0, 1, 2, 3, 4, 5, 6, 7, 8);
+child_arrays!(10; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9);
+child_arrays!(11; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10);
+child_arrays!(12; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11);
+child_arrays!(13; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12);
+child_arrays!(14; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13);
+child_arrays!(15; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14);
+child_arrays!(16; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+child_arrays!(17; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16);
+child_arrays!(18; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17);
+child_arrays!(19; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18);
+child_arrays!(20; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19);
+child_arrays!(21; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20);
+child_arrays!(22; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21);
+child_arrays!(23; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22);
+child_arrays!(24; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23);
+child_arrays!(25; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24);
+child_arrays!(26; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25);
+child_arrays!(27; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26);
+child_arrays!(28; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27);
+child_arrays!(29; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28);
+child_arrays!(30; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29);
+child_arrays!(31; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30);
+child_arrays!(32; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31);
+
+impl<'op, 'node, 't: 'op, T, C> ChildWriteGuard<'op, 'node, 't, T, C>
+    where C: ChildArray<Option<NodeWriteGuard<'op, 'node, 't, T, C>>> {
+
+    /// Split into a write guard for each child which exists, consuming self. Unlike
+    /// `into_all_children`, the array has the length of the branch factor by construction.
+    pub fn split_children(mut self) -> <C as ChildArray<Option<NodeWriteGuard<'op, 'node, 't, T, C>>>>::Array {
+        C::from_fn(|branch| unsafe {
+            self.make_child_write_guard(branch).unwrap()
+        })
+    }
+}
+
+impl<'op, 'node, 't: 'op, T, C> NodeWriteGuard<'op, 'node, 't, T, C>
+    where C: ChildArray<Option<NodeWriteGuard<'op, 'node, 't, T, C>>> {
+
+    /// Split into mutable access to the element, and a write guard for each child which exists,
+    /// consuming self.
+    pub fn into_split_children(self)
+        -> (&'node mut T, <C as ChildArray<Option<NodeWriteGuard<'op, 'node, 't, T, C>>>>::Array) {
+        let (elem, children) = self.into_split();
+        (elem, children.split_children())
+    }
+}
@@ -0,0 +1,636 @@
+//! An ordered map based on an AVL tree.
+
+use {Tree, ChildId, NodeIndex, NodeReadGuard, TreeWriteTraverser, IntoReadGuard, IntoWriteGuard, GetElemMut};
+
+use std::borrow::Borrow;
+use std::cmp::{self, Ordering};
+use std::ops::{RangeBounds, Bound};
+use std::iter::FromIterator;
+use std::fmt::{Debug, Formatter};
+use std::fmt;
+use std::mem;
+use std::vec::Vec;
+
+/// The element stored in each node of the map's tree.
+struct MapNode<K, V> {
+    key: K,
+    value: V,
+    height: usize,
+}
+
+type MapReadGuard<'a, K, V> = NodeReadGuard<'a, MapNode<K, V>, [ChildId; 2]>;
+type MapTraverser<'op, 't, K, V> = TreeWriteTraverser<'op, 't, MapNode<K, V>, [ChildId; 2]>;
+
+fn height<K, V>(node: Option<MapReadGuard<K, V>>) -> usize {
+    node.map(|node| node.height).unwrap_or(0)
+}
+
+/// The heights of the pointed-at node's left and right subtrees.
+fn child_heights<K, V>(trav: &MapTraverser<K, V>) -> (usize, usize) {
+    let guard = trav.into_read_guard();
+    (height(guard.child(0).unwrap()), height(guard.child(1).unwrap()))
+}
+
+/// Left height minus right height of the pointed-at node.
+fn balance_factor<K, V>(trav: &MapTraverser<K, V>) -> isize {
+    let (left, right) = child_heights(trav);
+    left as isize - right as isize
+}
+
+/// Recompute the pointed-at node's height from its children.
+fn update_height<K, V>(trav: &mut MapTraverser<K, V>) {
+    let (left, right) = child_heights(trav);
+    trav.height = cmp::max(left, right) + 1;
+}
+
+/// Recompute the heights of a demoted node and its promoted parent after a rotation, given that the
+/// traverser points to the promoted node.
+fn update_rotated<K, V>(trav: &mut MapTraverser<K, V>, demoted_branch: usize) {
+    trav.seek_child(demoted_branch).unwrap();
+    update_height(trav);
+    trav.seek_parent().unwrap();
+    update_height(trav);
+}
+
+/// Restore the AVL invariant at the pointed-at node, given that both its subtrees are balanced and
+/// differ in height by no more than 2. The traverser remains at the same position in the tree.
+fn rebalance<K, V>(trav: &mut MapTraverser<K, V>) {
+    let balance = balance_factor(trav);
+    if balance > 1 {
+        trav.seek_child(0).unwrap();
+        if balance_factor(trav) < 0 {
+            trav.rotate_left().unwrap();
+            update_rotated(trav, 0);
+        }
+        trav.seek_parent().unwrap();
+        trav.rotate_right().unwrap();
+        update_rotated(trav, 1);
+    } else if balance < -1 {
+        trav.seek_child(1).unwrap();
+        if balance_factor(trav) > 0 {
+            trav.rotate_right().unwrap();
+            update_rotated(trav, 1);
+        }
+        trav.seek_parent().unwrap();
+        trav.rotate_left().unwrap();
+        update_rotated(trav, 0);
+    } else {
+        update_height(trav);
+    }
+}
+
+/// Rebalance every node from the pointed-at node up to the root.
+fn rebalance_to_root<K, V>(trav: &mut MapTraverser<K, V>) {
+    loop {
+        rebalance(trav);
+        if trav.seek_parent().is_err() {
+            break;
+        }
+    }
+}
+
+/// Remove the pointed-at node, which must have a parent and no more than one child, moving its
+/// child into its place. Afterwards, the traverser points to the removed node's former parent.
+fn unlink<K, V>(trav: &mut MapTraverser<K, V>) -> MapNode<K, V> {
+    let child = match trav.detach_child(0) {
+        Ok(child) => Some(child),
+        Err(_) => trav.detach_child(1).ok(),
+    };
+    let branch = trav.this_branch_index().unwrap();
+    trav.seek_parent().unwrap();
+    let node = trav.detach_child(branch).unwrap();
+    if let Some(child) = child {
+        trav.into_write_guard().children().put_child_tree(branch, child).unwrap();
+    }
+    node.into_elem()
+}
+
+/// Remove the pointed-at node from the tree, rebalancing the tree, and returning its entry.
+fn remove_at<K, V>(mut trav: MapTraverser<K, V>) -> (K, V) {
+    if trav.has_child(0).unwrap() && trav.has_child(1).unwrap() {
+        // seek the in-order successor, which has no left child
+        trav.seek_child(1).unwrap();
+        let mut depth = 1;
+        while trav.seek_child(0).is_ok() {
+            depth += 1;
+        }
+
+        // remove the successor, and climb back up to the node being removed
+        let successor = unlink(&mut trav);
+        for _ in 1..depth {
+            rebalance(&mut trav);
+            trav.seek_parent().unwrap();
+        }
+
+        // the successor's entry replaces the removed entry
+        let key = mem::replace(&mut trav.key, successor.key);
+        let value = mem::replace(&mut trav.value, successor.value);
+        rebalance_to_root(&mut trav);
+        (key, value)
+    } else if trav.this_branch_index().is_ok() {
+        let MapNode { key, value, .. } = unlink(&mut trav);
+        rebalance_to_root(&mut trav);
+        (key, value)
+    } else {
+        // the root is being removed, so its child (if any) becomes the new root
+        let child = match trav.detach_child(0) {
+            Ok(child) => Some(child),
+            Err(_) => trav.detach_child(1).ok(),
+        };
+        let root = trav.detach_this();
+        let op = root.op;
+        let MapNode { key, value, .. } = root.into_elem();
+        if let Some(child) = child {
+            op.try_put_root_tree(child).unwrap();
+        }
+        (key, value)
+    }
+}
+
+/// Push a node and its chain of left descendants onto an iteration stack.
+fn push_left_spine<'a, K, V>(stack: &mut Vec<MapReadGuard<'a, K, V>>, mut node: Option<MapReadGuard<'a, K, V>>) {
+    while let Some(curr) = node {
+        node = curr.child(0).unwrap();
+        stack.push(curr);
+    }
+}
+
+/// An ordered map, implemented as an AVL tree stored in a bonzai `Tree`.
+pub struct BonzaiMap<K, V> {
+    tree: Tree<MapNode<K, V>, [ChildId; 2]>,
+    len: usize,
+}
+impl<K: Ord, V> BonzaiMap<K, V> {
+    /// Create a new, empty map.
+    pub fn new() -> Self {
+        BonzaiMap {
+            tree: Tree::new(),
+            len: 0,
+        }
+    }
+
+    /// The number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove all entries from the map.
+    pub fn clear(&mut self) {
+        self.tree = Tree::new();
+        self.len = 0;
+    }
+
+    fn find<Q: ?Sized>(&self, key: &Q) -> Option<MapReadGuard<K, V>> where K: Borrow<Q>, Q: Ord {
+        let mut curr = self.tree.read_root();
+        while let Some(node) = curr {
+            curr = match key.cmp(node.key.borrow()) {
+                Ordering::Less => node.child(0).unwrap(),
+                Ordering::Greater => node.child(1).unwrap(),
+                Ordering::Equal => return Some(node),
+            };
+        }
+        None
+    }
+
+    fn node_at(&self, index: NodeIndex) -> &MapNode<K, V> {
+        unsafe {
+            NodeReadGuard::new(&self.tree, index.index).elem
+        }
+    }
+
+    fn node_at_mut(&mut self, index: NodeIndex) -> &mut MapNode<K, V> {
+        self.tree.get_elem_mut(index).unwrap()
+    }
+
+    /// Get a reference to the value corresponding to the key.
+    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V> where K: Borrow<Q>, Q: Ord {
+        self.find(key).map(|node| &node.elem.value)
+    }
+
+    /// Get references to the key and value corresponding to the key.
+    pub fn get_key_value<Q: ?Sized>(&self, key: &Q) -> Option<(&K, &V)> where K: Borrow<Q>, Q: Ord {
+        self.find(key).map(|node| (&node.elem.key, &node.elem.value))
+    }
+
+    /// Get a mutable reference to the value corresponding to the key.
+    pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V> where K: Borrow<Q>, Q: Ord {
+        let index = self.find(key)?.index();
+        Some(&mut self.node_at_mut(index).value)
+    }
+
+    /// Whether the map contains a value for the key.
+    pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: Ord {
+        self.find(key).is_some()
+    }
+
+    /// Insert an entry, returning the index of its node, and the old value if the key was present.
+    fn insert_index(&mut self, key: K, value: V) -> (NodeIndex, Option<V>) {
+        let mut op = self.tree.operation();
+        let node = MapNode {
+            key,
+            value,
+            height: 1,
+        };
+
+        if op.read_root().is_none() {
+            op.put_root_elem(node);
+            self.len += 1;
+            return (op.read_root().unwrap().index(), None);
+        }
+
+        let mut trav = op.traverse_root().unwrap();
+        loop {
+            let branch = match node.key.cmp(&trav.key) {
+                Ordering::Less => 0,
+                Ordering::Greater => 1,
+                Ordering::Equal => {
+                    let old = mem::replace(&mut trav.value, node.value);
+                    return ((&trav).into_read_guard().index(), Some(old));
+                },
+            };
+            if trav.has_child(branch).unwrap() {
+                trav.seek_child(branch).unwrap();
+            } else {
+                (&mut trav).into_write_guard().children().put_child_elem(branch, node).unwrap();
+                trav.seek_child(branch).unwrap();
+                break;
+            }
+        }
+
+        // rotations relink nodes, but never move them, so the index stays valid
+        let index = (&trav).into_read_guard().index();
+        rebalance_to_root(&mut trav);
+        self.len += 1;
+        (index, None)
+    }
+
+    /// Insert a key-value pair, returning the previous value if the key was present. If the
+    /// key was present, the key itself is not updated.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_index(key, value).1
+    }
+
+    fn remove_index(&mut self, index: NodeIndex) -> (K, V) {
+        let entry = {
+            let mut op = self.tree.operation();
+            let trav = op.traverse_from(index).unwrap();
+            remove_at(trav)
+        };
+        self.len -= 1;
+        entry
+    }
+
+    /// Remove a key from the map, returning its value if it was present.
+    pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V> where K: Borrow<Q>, Q: Ord {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Remove a key from the map, returning the stored key and value if it was present.
+    pub fn remove_entry<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)> where K: Borrow<Q>, Q: Ord {
+        let index = self.find(key)?.index();
+        Some(self.remove_index(index))
+    }
+
+    /// Get the entry for a key, for in-place manipulation.
+    pub fn entry(&mut self, key: K) -> Entry<K, V> {
+        let found = self.find(&key).map(|node| node.index());
+        match found {
+            Some(index) => Entry::Occupied(OccupiedEntry {
+                map: self,
+                index,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                map: self,
+                key,
+            }),
+        }
+    }
+
+    /// Iterate over the entries of the map, in order of their keys.
+    pub fn iter(&self) -> Iter<K, V> {
+        let mut stack = Vec::new();
+        push_left_spine(&mut stack, self.tree.read_root());
+        Iter {
+            stack,
+            remaining: self.len,
+        }
+    }
+
+    /// Iterate over the keys of the map, in order.
+    pub fn keys(&self) -> Keys<K, V> {
+        Keys {
+            inner: self.iter()
+        }
+    }
+
+    /// Iterate over the values of the map, in order of their keys.
+    pub fn values(&self) -> Values<K, V> {
+        Values {
+            inner: self.iter()
+        }
+    }
+
+    /// Iterate over the entries with keys in the given range, in order of their keys.
+    ///
+    /// Panics if the range start is greater than the range end, or if the range start and end
+    /// are equal and both excluded.
+    pub fn range<Q: ?Sized, R>(&self, range: R) -> Range<K, V> where K: Borrow<Q>, Q: Ord, R: RangeBounds<Q> {
+        match (range.start_bound(), range.end_bound()) {
+            (Bound::Excluded(start), Bound::Excluded(end)) if start == end => {
+                panic!("range start and end are equal and excluded in BonzaiMap");
+            },
+            (Bound::Included(start), Bound::Included(end)) |
+            (Bound::Included(start), Bound::Excluded(end)) |
+            (Bound::Excluded(start), Bound::Included(end)) |
+            (Bound::Excluded(start), Bound::Excluded(end)) if start > end => {
+                panic!("range start is greater than range end in BonzaiMap");
+            },
+            _ => (),
+        };
+
+        // stack up the path to the first node in range
+        let mut stack = Vec::new();
+        let mut curr = self.tree.read_root();
+        while let Some(node) = curr {
+            let in_range = {
+                let key: &Q = node.key.borrow();
+                match range.start_bound() {
+                    Bound::Included(start) => key >= start,
+                    Bound::Excluded(start) => key > start,
+                    Bound::Unbounded => true,
+                }
+            };
+            if in_range {
+                curr = node.child(0).unwrap();
+                stack.push(node);
+            } else {
+                curr = node.child(1).unwrap();
+            }
+        }
+
+        // find the first node past the range, where iteration stops
+        let mut end = None;
+        let mut curr = self.tree.read_root();
+        while let Some(node) = curr {
+            let past_end = {
+                let key: &Q = node.key.borrow();
+                match range.end_bound() {
+                    Bound::Included(end) => key > end,
+                    Bound::Excluded(end) => key >= end,
+                    Bound::Unbounded => false,
+                }
+            };
+            if past_end {
+                end = Some(node.index());
+                curr = node.child(0).unwrap();
+            } else {
+                curr = node.child(1).unwrap();
+            }
+        }
+
+        Range {
+            stack,
+            end,
+        }
+    }
+}
+impl<K: Ord, V> Default for BonzaiMap<K, V> {
+    fn default() -> Self {
+        BonzaiMap::new()
+    }
+}
+impl<K: Ord + Debug, V: Debug> Debug for BonzaiMap<K, V> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+impl<K: Ord, V> FromIterator<(K, V)> for BonzaiMap<K, V> {
+    fn from_iter<I: IntoIterator<Item=(K, V)>>(iter: I) -> Self {
+        let mut map = BonzaiMap::new();
+        map.extend(iter);
+        map
+    }
+}
+impl<K: Ord, V> Extend<(K, V)> for BonzaiMap<K, V> {
+    fn extend<I: IntoIterator<Item=(K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+impl<'a, K: Ord, V> IntoIterator for &'a BonzaiMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+/// A view into a single entry of a map, which may be vacant or occupied.
+pub enum Entry<'a, K: 'a, V: 'a> {
+    Vacant(VacantEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V>),
+}
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+    /// Insert the default value if the entry is vacant, and return a mutable reference to the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Insert the result of the default function if the entry is vacant, and return a mutable
+    /// reference to the value.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default()),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Modify the value in-place if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            },
+            vacant => vacant,
+        }
+    }
+
+    /// The key of this entry.
+    pub fn key(&self) -> &K {
+        match self {
+            &Entry::Vacant(ref entry) => entry.key(),
+            &Entry::Occupied(ref entry) => entry.key(),
+        }
+    }
+}
+impl<'a, K: Ord + Debug, V: Debug> Debug for Entry<'a, K, V> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &Entry::Vacant(ref entry) => f.debug_tuple("Entry").field(entry).finish(),
+            &Entry::Occupied(ref entry) => f.debug_tuple("Entry").field(entry).finish(),
+        }
+    }
+}
+
+/// A vacant entry of a map.
+pub struct VacantEntry<'a, K: 'a, V: 'a> {
+    map: &'a mut BonzaiMap<K, V>,
+    key: K,
+}
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+    /// The key which would be used to insert into this entry.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Take ownership of the key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Insert a value into this entry, returning a mutable reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let (index, _) = self.map.insert_index(self.key, value);
+        &mut self.map.node_at_mut(index).value
+    }
+}
+impl<'a, K: Ord + Debug, V> Debug for VacantEntry<'a, K, V> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.debug_tuple("VacantEntry").field(self.key()).finish()
+    }
+}
+
+/// An occupied entry of a map.
+pub struct OccupiedEntry<'a, K: 'a, V: 'a> {
+    map: &'a mut BonzaiMap<K, V>,
+    index: NodeIndex,
+}
+impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+    /// The key stored in this entry.
+    pub fn key(&self) -> &K {
+        &self.map.node_at(self.index).key
+    }
+
+    /// Get a reference to the value in this entry.
+    pub fn get(&self) -> &V {
+        &self.map.node_at(self.index).value
+    }
+
+    /// Get a mutable reference to the value in this entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.node_at_mut(self.index).value
+    }
+
+    /// Convert into a mutable reference to the value in this entry, with the lifetime of the map.
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.node_at_mut(self.index).value
+    }
+
+    /// Replace the value in this entry, returning the old value.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Remove this entry from the map, returning the value.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Remove this entry from the map, returning the stored key and value.
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.remove_index(self.index)
+    }
+}
+impl<'a, K: Ord + Debug, V: Debug> Debug for OccupiedEntry<'a, K, V> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("OccupiedEntry")
+            .field("key", self.key())
+            .field("value", self.get())
+            .finish()
+    }
+}
+
+/// An iterator over the entries of a map, in order of their keys.
+pub struct Iter<'a, K: 'a, V: 'a> {
+    stack: Vec<MapReadGuard<'a, K, V>>,
+    remaining: usize,
+}
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = self.stack.pop()?;
+        push_left_spine(&mut self.stack, node.child(1).unwrap());
+        self.remaining -= 1;
+        Some((&node.elem.key, &node.elem.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+/// An iterator over the keys of a map, in order.
+pub struct Keys<'a, K: 'a, V: 'a> {
+    inner: Iter<'a, K, V>,
+}
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+impl<'a, K, V> ExactSizeIterator for Keys<'a, K, V> {}
+
+/// An iterator over the values of a map, in order of their keys.
+pub struct Values<'a, K: 'a, V: 'a> {
+    inner: Iter<'a, K, V>,
+}
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+impl<'a, K, V> ExactSizeIterator for Values<'a, K, V> {}
+
+/// An iterator over a range of entries of a map, in order of their keys.
+pub struct Range<'a, K: 'a, V: 'a> {
+    stack: Vec<MapReadGuard<'a, K, V>>,
+    end: Option<NodeIndex>,
+}
+impl<'a, K, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = self.stack.pop()?;
+        if Some(node.index()) == self.end {
+            self.stack.clear();
+            return None;
+        }
+        push_left_spine(&mut self.stack, node.child(1).unwrap());
+        Some((&node.elem.key, &node.elem.value))
+    }
+}
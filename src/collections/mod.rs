@@ -0,0 +1,10 @@
+//! Ordered collections built on top of bonzai trees.
+//!
+//! These are balanced (AVL) binary search trees stored in a `Tree<_, [ChildId; 2]>`, with an
+//! interface modeled after `std::collections::BTreeMap` and `BTreeSet`.
+
+pub mod map;
+pub mod set;
+
+pub use self::map::BonzaiMap;
+pub use self::set::BonzaiSet;
@@ -0,0 +1,140 @@
+//! An ordered set based on an AVL tree.
+
+use super::map::{self, BonzaiMap};
+
+use std::borrow::Borrow;
+use std::ops::RangeBounds;
+use std::iter::FromIterator;
+use std::fmt::{Debug, Formatter};
+use std::fmt;
+
+/// An ordered set, implemented as an AVL tree stored in a bonzai `Tree`.
+pub struct BonzaiSet<K> {
+    map: BonzaiMap<K, ()>,
+}
+impl<K: Ord> BonzaiSet<K> {
+    /// Create a new, empty set.
+    pub fn new() -> Self {
+        BonzaiSet {
+            map: BonzaiMap::new(),
+        }
+    }
+
+    /// The number of elements in the set.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Whether the set contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Remove all elements from the set.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Whether the set contains the value.
+    pub fn contains<Q: ?Sized>(&self, value: &Q) -> bool where K: Borrow<Q>, Q: Ord {
+        self.map.contains_key(value)
+    }
+
+    /// Get a reference to the element in the set equal to the value.
+    pub fn get<Q: ?Sized>(&self, value: &Q) -> Option<&K> where K: Borrow<Q>, Q: Ord {
+        self.map.get_key_value(value).map(|(key, _)| key)
+    }
+
+    /// Insert a value into the set, returning whether it was not already present.
+    pub fn insert(&mut self, value: K) -> bool {
+        self.map.insert(value, ()).is_none()
+    }
+
+    /// Remove a value from the set, returning whether it was present.
+    pub fn remove<Q: ?Sized>(&mut self, value: &Q) -> bool where K: Borrow<Q>, Q: Ord {
+        self.map.remove(value).is_some()
+    }
+
+    /// Remove and return the element in the set equal to the value, if present.
+    pub fn take<Q: ?Sized>(&mut self, value: &Q) -> Option<K> where K: Borrow<Q>, Q: Ord {
+        self.map.remove_entry(value).map(|(key, _)| key)
+    }
+
+    /// Iterate over the elements of the set, in order.
+    pub fn iter(&self) -> Iter<K> {
+        Iter {
+            inner: self.map.keys(),
+        }
+    }
+
+    /// Iterate over the elements of the set in the given range, in order.
+    ///
+    /// Panics if the range start is greater than the range end, or if the range start and end
+    /// are equal and both excluded.
+    pub fn range<Q: ?Sized, R>(&self, range: R) -> Range<K> where K: Borrow<Q>, Q: Ord, R: RangeBounds<Q> {
+        Range {
+            inner: self.map.range(range),
+        }
+    }
+}
+impl<K: Ord> Default for BonzaiSet<K> {
+    fn default() -> Self {
+        BonzaiSet::new()
+    }
+}
+impl<K: Ord + Debug> Debug for BonzaiSet<K> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+impl<K: Ord> FromIterator<K> for BonzaiSet<K> {
+    fn from_iter<I: IntoIterator<Item=K>>(iter: I) -> Self {
+        let mut set = BonzaiSet::new();
+        set.extend(iter);
+        set
+    }
+}
+impl<K: Ord> Extend<K> for BonzaiSet<K> {
+    fn extend<I: IntoIterator<Item=K>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+impl<'a, K: Ord> IntoIterator for &'a BonzaiSet<K> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Iter<'a, K> {
+        self.iter()
+    }
+}
+
+/// An iterator over the elements of a set, in order.
+pub struct Iter<'a, K: 'a> {
+    inner: map::Keys<'a, K, ()>,
+}
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+impl<'a, K> ExactSizeIterator for Iter<'a, K> {}
+
+/// An iterator over a range of elements of a set, in order.
+pub struct Range<'a, K: 'a> {
+    inner: map::Range<'a, K, ()>,
+}
+impl<'a, K> Iterator for Range<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next().map(|(key, _)| key)
+    }
+}
@@ -0,0 +1,176 @@
+//! Lockstep walks and structural comparison of two trees.
+
+use {Tree, ChildId, NodeReadGuard, BranchPath, PreOrder};
+
+use core::array::FixedSizeArray;
+use std::vec::Vec;
+
+/// A difference between an old and a new tree, found by `Tree::diff`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DiffOp {
+    /// A subtree which is only present in the new tree.
+    Inserted {
+        path: BranchPath,
+    },
+    /// A subtree which is only present in the old tree.
+    Removed {
+        path: BranchPath,
+    },
+    /// A node which is present in both trees, with unequal elements.
+    Changed {
+        path: BranchPath,
+    },
+    /// A subtree which was removed from one path, and inserted unchanged at another.
+    Moved {
+        from: BranchPath,
+        to: BranchPath,
+    },
+}
+
+/// Whether two subtrees have the same shape and equal elements.
+fn subtrees_eq<T: PartialEq, C: FixedSizeArray<ChildId>>(a: NodeReadGuard<T, C>, b: NodeReadGuard<T, C>) -> bool {
+    let mut stack = vec![(a, b)];
+    while let Some((a, b)) = stack.pop() {
+        if a.elem != b.elem {
+            return false;
+        }
+        let mut branch = 0;
+        while let (Ok(a_child), Ok(b_child)) = (a.child(branch), b.child(branch)) {
+            match (a_child, b_child) {
+                (Some(a_child), Some(b_child)) => stack.push((a_child, b_child)),
+                (None, None) => (),
+                _ => return false,
+            };
+            branch += 1;
+        }
+    }
+    true
+}
+
+/// The node at a path, which is known to exist.
+fn node_at<'tree, T, C: FixedSizeArray<ChildId>>(tree: &'tree Tree<T, C>, path: &[usize]) -> NodeReadGuard<'tree, T, C> {
+    path.iter().fold(tree.read_root().unwrap(), |node, &branch| node.child(branch).unwrap().unwrap())
+}
+
+impl<T: PartialEq, C: FixedSizeArray<ChildId>> Tree<T, C> {
+    /// Compare this tree, as the old tree, to a new tree, producing the differences in pre-order.
+    ///
+    /// Inserted and removed subtrees are reported once, not for each of their nodes. A removed
+    /// subtree which is equal to an inserted subtree is reported as moved instead. Pairing them
+    /// compares subtrees, so it is O(R * I * M) where R and I are the numbers of removed and
+    /// inserted subtrees, and M is their size.
+    pub fn diff(&self, new: &Tree<T, C>) -> impl Iterator<Item=DiffOp> {
+        let mut ops = Vec::new();
+        let root = (BranchPath::root(), self.read_root(), new.read_root());
+        let walk = PreOrder::new(Some(root), |&(ref path, ref old_node, ref new_node), children| {
+            if let (&Some(ref old_node), &Some(ref new_node)) = (old_node, new_node) {
+                let mut branch = 0;
+                while let (Ok(old_child), Ok(new_child)) = (old_node.child(branch), new_node.child(branch)) {
+                    children.push((path.child(branch), old_child, new_child));
+                    branch += 1;
+                }
+            }
+        });
+        for (path, old_node, new_node) in walk {
+            match (old_node, new_node) {
+                (Some(old_node), Some(new_node)) => if old_node.elem != new_node.elem {
+                    ops.push(DiffOp::Changed {
+                        path,
+                    });
+                },
+                (Some(_), None) => ops.push(DiffOp::Removed {
+                    path,
+                }),
+                (None, Some(_)) => ops.push(DiffOp::Inserted {
+                    path,
+                }),
+                (None, None) => (),
+            };
+        }
+
+        // pair up removed and inserted subtrees which are equal
+        let mut paired = vec![false; ops.len()];
+        for i in 0..ops.len() {
+            let to = match ops[i] {
+                DiffOp::Inserted {
+                    ref path,
+                } => path.clone(),
+                _ => continue,
+            };
+            let removed = (0..ops.len())
+                .find(|&j| !paired[j] && match ops[j] {
+                    DiffOp::Removed {
+                        ref path,
+                    } => subtrees_eq(node_at(self, path), node_at(new, &to)),
+                    _ => false,
+                });
+            if let Some(j) = removed {
+                let from = match ops[j] {
+                    DiffOp::Removed {
+                        ref path,
+                    } => path.clone(),
+                    _ => unreachable!(),
+                };
+                paired[j] = true;
+                ops[i] = DiffOp::Moved {
+                    from,
+                    to,
+                };
+            }
+        }
+
+        let ops: Vec<DiffOp> = ops.into_iter().zip(paired)
+            .filter(|&(_, paired)| !paired)
+            .map(|(op, _)| op)
+            .collect();
+        ops.into_iter()
+    }
+}
+
+impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
+    /// Walk this tree and another tree of the same branch factor in lockstep, in pre-order,
+    /// yielding the elements at each position which is present in either tree.
+    pub fn zip<'a, B>(&'a self, other: &'a Tree<B, C>) -> Zip<'a, T, B, C> {
+        Zip {
+            walk: PreOrder::new(Some((self.read_root(), other.read_root())), zip_children),
+        }
+    }
+}
+
+/// The nodes at the same position in two trees, either of which may be missing.
+type ZipPair<'a, A, B, C> = (Option<NodeReadGuard<'a, A, C>>, Option<NodeReadGuard<'a, B, C>>);
+
+/// Push the pairs of children of a pair of nodes, for every branch.
+fn zip_children<'a, A, B, C>(&(ref a, ref b): &ZipPair<'a, A, B, C>, children: &mut Vec<ZipPair<'a, A, B, C>>)
+    where C: FixedSizeArray<ChildId> {
+    if a.is_none() && b.is_none() {
+        return;
+    }
+    let mut branch = 0;
+    loop {
+        let a_child = a.as_ref().map(|a| a.child(branch));
+        let b_child = b.as_ref().map(|b| b.child(branch));
+        match (a_child, b_child) {
+            (Some(Err(_)), _) | (_, Some(Err(_))) => break,
+            (a_child, b_child) => children.push((
+                a_child.and_then(Result::unwrap),
+                b_child.and_then(Result::unwrap),
+            )),
+        };
+        branch += 1;
+    }
+}
+
+/// An iterator which walks two trees in lockstep, created by `Tree::zip`.
+pub struct Zip<'a, A: 'a, B: 'a, C: FixedSizeArray<ChildId> + 'a> {
+    walk: PreOrder<ZipPair<'a, A, B, C>, fn(&ZipPair<'a, A, B, C>, &mut Vec<ZipPair<'a, A, B, C>>)>,
+}
+impl<'a, A, B, C: FixedSizeArray<ChildId>> Iterator for Zip<'a, A, B, C> {
+    type Item = (Option<&'a A>, Option<&'a B>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.walk.by_ref()
+            .find(|&(ref a, ref b)| a.is_some() || b.is_some())
+            .map(|(a, b)| (a.map(|a| a.elem), b.map(|b| b.elem)))
+    }
+}
@@ -0,0 +1,171 @@
+//! Drawing trees as text, in the style of the `tree` command.
+
+use {Tree, ChildId, NodeReadGuard, PreOrder};
+
+use core::array::FixedSizeArray;
+use std::fmt::{Display, Formatter};
+use std::fmt;
+use std::vec::Vec;
+use std::string::String;
+
+/// The function which formats an element with its `Display` implementation.
+pub type DisplayElem<T> = fn(&T, &mut Formatter) -> Result<(), fmt::Error>;
+
+impl<T: Display, C: FixedSizeArray<ChildId>> Tree<T, C> {
+    /// Draw the tree with box-drawing characters, formatting the elements with `Display`.
+    pub fn display_tree(&self) -> DisplayTree<T, C, DisplayElem<T>> {
+        self.display_tree_with(<T as Display>::fmt)
+    }
+}
+
+impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
+    /// Draw the tree with box-drawing characters, formatting the elements with a function.
+    pub fn display_tree_with<F>(&self, format: F) -> DisplayTree<T, C, F>
+        where F: Fn(&T, &mut Formatter) -> Result<(), fmt::Error> {
+        DisplayTree::new(self.read_root(), format)
+    }
+}
+
+impl<'tree, T: Display, C: FixedSizeArray<ChildId>> NodeReadGuard<'tree, T, C> {
+    /// Draw the subtree rooted at this node, as in `Tree::display_tree`.
+    pub fn display_tree(self) -> DisplayTree<'tree, T, C, DisplayElem<T>> {
+        DisplayTree::new(Some(self), <T as Display>::fmt)
+    }
+}
+
+/// A tree drawing which implements `Display`, created by `Tree::display_tree`. Each child is
+/// drawn on its own line, labeled with its branch index:
+///
+/// ```text
+/// a
+/// ├── 0: b
+/// │   └── 1: d
+/// └── 1: c
+/// ```
+///
+/// Empty branches are drawn with the label `∅`, unless they are omitted.
+pub struct DisplayTree<'tree, T: 'tree, C: FixedSizeArray<ChildId> + 'tree, F> {
+    root: Option<NodeReadGuard<'tree, T, C>>,
+    format: F,
+    max_depth: Option<usize>,
+    omit_empty: bool,
+    ascii: bool,
+}
+impl<'tree, T, C: FixedSizeArray<ChildId>, F> DisplayTree<'tree, T, C, F>
+    where F: Fn(&T, &mut Formatter) -> Result<(), fmt::Error> {
+
+    fn new(root: Option<NodeReadGuard<'tree, T, C>>, format: F) -> Self {
+        DisplayTree {
+            root,
+            format,
+            max_depth: None,
+            omit_empty: false,
+            ascii: false,
+        }
+    }
+
+    /// Only draw nodes up to a certain depth, where the root has a depth of 0. Nodes with
+    /// children beyond that depth are drawn with a single `...` child.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Whether to leave out branches which have no child.
+    pub fn omit_empty(mut self, omit_empty: bool) -> Self {
+        self.omit_empty = omit_empty;
+        self
+    }
+
+    /// Whether to draw with ASCII characters, instead of box-drawing characters.
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+}
+impl<'tree, T, C: FixedSizeArray<ChildId>, F> Display for DisplayTree<'tree, T, C, F>
+    where F: Fn(&T, &mut Formatter) -> Result<(), fmt::Error> {
+
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        let (tee, elbow, pipe, space) = if self.ascii {
+            ("|-- ", "`-- ", "|   ", "    ")
+        } else {
+            ("├── ", "└── ", "│   ", "    ")
+        };
+
+        // a line to draw, after the prefix which it shares with its siblings
+        enum Line<'tree, T: 'tree, C: FixedSizeArray<ChildId> + 'tree> {
+            Node(NodeReadGuard<'tree, T, C>),
+            Empty,
+            Elided,
+        }
+
+        let root = match self.root {
+            Some(ref root) => unsafe { NodeReadGuard::new(root.tree, root.index) },
+            None => return Ok(()),
+        };
+        // each entry is a line, its shared prefix, whether it is the last sibling, its branch
+        // index, and its depth
+        let first = (Line::Node(root), String::new(), true, None, 0);
+        let walk = PreOrder::new(Some(first), |&(ref line, ref prefix, last, _, depth), lines| {
+            let node = match line {
+                &Line::Node(ref node) => node,
+                _ => return,
+            };
+            let child_prefix = if depth == 0 {
+                String::new()
+            } else {
+                prefix.clone() + if last { space } else { pipe }
+            };
+            let mut children = Vec::new();
+            let mut child_branch = 0;
+            while let Ok(child) = node.child(child_branch) {
+                match child {
+                    Some(child) => children.push((Line::Node(child), Some(child_branch))),
+                    None if !self.omit_empty => children.push((Line::Empty, Some(child_branch))),
+                    None => (),
+                };
+                child_branch += 1;
+            }
+            if self.max_depth.map(|max_depth| depth >= max_depth).unwrap_or(false) {
+                let has_child = children.iter().any(|&(ref line, _)| match line {
+                    &Line::Node(_) => true,
+                    _ => false,
+                });
+                children.clear();
+                if has_child {
+                    children.push((Line::Elided, None));
+                }
+            }
+
+            let num_children = children.len();
+            for (i, (line, child_branch)) in children.into_iter().enumerate() {
+                lines.push((line, child_prefix.clone(), i + 1 == num_children, child_branch, depth + 1));
+            }
+        });
+        for (line, prefix, last, branch, depth) in walk {
+            if let Some(branch) = branch {
+                f.write_str(&prefix)?;
+                f.write_str(if last { elbow } else { tee })?;
+                write!(f, "{}: ", branch)?;
+            } else if depth > 0 {
+                f.write_str(&prefix)?;
+                f.write_str(if last { elbow } else { tee })?;
+            }
+            let node = match line {
+                Line::Node(node) => node,
+                Line::Empty => {
+                    f.write_str("∅\n")?;
+                    continue;
+                },
+                Line::Elided => {
+                    f.write_str("...\n")?;
+                    continue;
+                },
+            };
+            (self.format)(node.elem, f)?;
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
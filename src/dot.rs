@@ -0,0 +1,153 @@
+//! Export of trees and their node storage as Graphviz DOT graphs.
+
+use {Tree, TreeOperation, Node, ParentId, ChildId};
+use validate::live_nodes;
+
+use core::array::FixedSizeArray;
+use std::fmt::{Debug, Write};
+use std::fmt;
+use std::string::String;
+
+/// Options for `Tree::to_dot_with`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DotOptions {
+    /// Draw garbage nodes, which are normally left out, and the child links of garbage nodes.
+    pub garbage: bool,
+    /// Group the nodes by the chunk of the node vec which stores them.
+    pub chunks: bool,
+}
+
+/// Escape a string for use within a double-quoted DOT string.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl<T: Debug, C: FixedSizeArray<ChildId>> Tree<T, C> {
+    /// Write the tree as a Graphviz DOT graph, as in `to_dot_with` with the default options.
+    pub fn to_dot(&self, out: &mut impl Write) -> fmt::Result {
+        self.to_dot_with(out, DotOptions::default())
+    }
+
+    /// Write the tree as a Graphviz DOT graph, such as into a `String`, for debugging. Each node is
+    /// labeled with its index in the node vec and its element. Child links are solid and labeled
+    /// with their branch index, while parent links are dashed, so that mismatched links stand out.
+    pub fn to_dot_with(&self, out: &mut impl Write, options: DotOptions) -> fmt::Result {
+        unsafe {
+            let nodes = &*self.nodes.get();
+            // if the parent links are broken, fall back to each node's own parent link, so that
+            // the corruption can still be drawn
+            let live = live_nodes(self).unwrap_or_else(|_| (0..nodes.len())
+                .map(|index| match &*nodes[index].get() {
+                    &Node::Garbage { .. } => false,
+                    &Node::Present {
+                        ref parent,
+                        ..
+                    } => parent.get() != ParentId::Garbage,
+                })
+                .collect());
+            let is_garbage = |index: usize| !live[index];
+
+            writeln!(out, "digraph tree {{")?;
+            writeln!(out, "    node [shape=box];")?;
+
+            // nodes, grouped by chunk if requested
+            let chunk_lens = if options.chunks {
+                nodes.chunk_lens()
+            } else {
+                vec![nodes.len()]
+            };
+            let mut index = 0;
+            for (chunk, &chunk_len) in chunk_lens.iter().enumerate() {
+                let indent = if options.chunks {
+                    writeln!(out, "    subgraph cluster_{} {{", chunk)?;
+                    writeln!(out, "        label=\"chunk {}\";", chunk)?;
+                    writeln!(out, "        style=dashed;")?;
+                    "        "
+                } else {
+                    "    "
+                };
+                for _ in 0..chunk_len {
+                    if !is_garbage(index) {
+                        if let &Node::Present {
+                            ref elem,
+                            ref parent,
+                            ..
+                        } = &*nodes[index].get() {
+                            let style = if parent.get() == ParentId::Detached {
+                                ", style=dotted"
+                            } else {
+                                ""
+                            };
+                            let label = format!("#{}\n{:?}", index, &*elem.get());
+                            writeln!(out, "{}n{} [label=\"{}\"{}];", indent, index, escape(&label), style)?;
+                        }
+                    } else if options.garbage {
+                        let label = match &*nodes[index].get() {
+                            &Node::Present {
+                                ref elem,
+                                ..
+                            } => format!("#{}\n{:?}", index, &*elem.get()),
+                            &Node::Garbage { .. } => format!("#{}", index),
+                        };
+                        writeln!(out, "{}n{} [label=\"{}\", style=filled, fillcolor=gray];",
+                                 indent, index, escape(&label))?;
+                    }
+                    index += 1;
+                }
+                if options.chunks {
+                    writeln!(out, "    }}")?;
+                }
+            }
+
+            // links
+            if let Some(root_index) = self.root.get() {
+                writeln!(out, "    root [shape=point];")?;
+                writeln!(out, "    root -> n{};", root_index)?;
+            }
+            for index in 0..nodes.len() {
+                let garbage = is_garbage(index);
+                if garbage && !options.garbage {
+                    continue;
+                }
+                let (parent, children) = match &*nodes[index].get() {
+                    &Node::Garbage {
+                        ref children,
+                    } => (None, children.as_slice()),
+                    &Node::Present {
+                        ref parent,
+                        ref children,
+                        ..
+                    } => (Some(parent.get()), (&*children.get()).as_slice()),
+                };
+                for (branch, child_id) in children.iter().enumerate() {
+                    if let Some(child_index) = child_id.index {
+                        let style = if garbage {
+                            ", color=gray"
+                        } else {
+                            ""
+                        };
+                        writeln!(out, "    n{} -> n{} [label=\"{}\"{}];", index, child_index, branch, style)?;
+                    }
+                }
+                if let Some(ParentId::Some {
+                    parent_index,
+                    this_branch,
+                }) = parent {
+                    writeln!(out, "    n{} -> n{} [label=\"{}\", style=dashed, color=gray, constraint=false];",
+                             index, parent_index, this_branch)?;
+                }
+            }
+
+            writeln!(out, "}}")
+        }
+    }
+}
+
+impl<'tree, T: Debug, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
+    /// Write the tree as a Graphviz DOT graph, as in `Tree::to_dot_with`. Within an operation,
+    /// detached subtrees are drawn with dotted outlines, and garbage which has not yet been
+    /// collected can be drawn.
+    pub fn to_dot_with(&self, out: &mut impl Write, options: DotOptions) -> fmt::Result {
+        self.tree.to_dot_with(out, options)
+    }
+}
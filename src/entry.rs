@@ -0,0 +1,158 @@
+//! Guided descent to a node, or to the vacant position where it would be inserted.
+
+use {TreeOperation, Node, ParentId, ChildId, NodeWriteGuard, ChildWriteGuard, Error, new_child_array};
+
+use core::array::FixedSizeArray;
+use std::cell::{UnsafeCell, Cell};
+use std::marker::PhantomData;
+use std::fmt::{Debug, Formatter};
+use std::fmt;
+
+impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
+    /// Descend from the root, following the branch which the guide returns for each element,
+    /// until the guide returns none, or the branch it returns has no child. This mirrors
+    /// `NodeReadGuard::descend`, but produces an entry which can be written to, or inserted into.
+    ///
+    /// Panics if the guide returns a branch index which is invalid for the branch factor.
+    pub fn descend_mut<'s, F>(&'s mut self, guide: F) -> Entry<'s, 'tree, T, C>
+        where F: FnMut(&T) -> Option<usize> {
+        match self.try_descend_mut(guide) {
+            Ok(entry) => entry,
+            Err(err) => panic!("descend guide returned {}", err),
+        }
+    }
+
+    /// Descend from the root, as in `descend_mut`, but fail instead of panicking if the guide
+    /// returns a branch index which is invalid for the branch factor.
+    pub fn try_descend_mut<'s, F>(&'s mut self, mut guide: F) -> Result<Entry<'s, 'tree, T, C>, Error>
+        where F: FnMut(&T) -> Option<usize> {
+
+        let self_immutable: &Self = self;
+
+        unsafe {
+            let nodes = &*self_immutable.tree.nodes.get();
+            let mut curr = match self_immutable.tree.root.get() {
+                Some(root_index) => root_index,
+                None => return Ok(Entry::Vacant(VacantChild {
+                    op: self_immutable,
+                    parent: None,
+                    branch: 0,
+                })),
+            };
+            loop {
+                if let &Node::Present {
+                    ref elem,
+                    ref children,
+                    ..
+                } = &*nodes[curr].get() {
+                    let branch = match guide(&*elem.get()) {
+                        Some(branch) => branch,
+                        None => return Ok(Entry::Occupied(NodeWriteGuard {
+                            op: self_immutable,
+                            index: curr,
+
+                            p1: PhantomData,
+                        })),
+                    };
+                    match (&*children.get()).as_slice().get(branch) {
+                        Some(&ChildId { index: Some(child_index) }) => curr = child_index,
+                        Some(&ChildId { index: None }) => return Ok(Entry::Vacant(VacantChild {
+                            op: self_immutable,
+                            parent: Some(curr),
+                            branch,
+                        })),
+                        None => return Err(Error::InvalidBranchIndex(branch)),
+                    };
+                } else {
+                    unreachable!("descended into garbage");
+                }
+            }
+        }
+    }
+}
+
+/// The result of `TreeOperation::descend_mut`, which is either the node at which the descent
+/// stopped, or the vacant position at which it ran out of nodes.
+pub enum Entry<'op, 't: 'op, T: 'op, C: FixedSizeArray<ChildId> + 'op> {
+    Occupied(NodeWriteGuard<'op, 'op, 't, T, C>),
+    Vacant(VacantChild<'op, 't, T, C>),
+}
+impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> Entry<'op, 't, T, C> {
+    /// Write to the occupied node, or insert an element at the vacant position.
+    pub fn or_insert(self, elem: T) -> NodeWriteGuard<'op, 'op, 't, T, C> {
+        self.or_insert_with(|| elem)
+    }
+
+    /// Write to the occupied node, or insert the result of a function at the vacant position.
+    pub fn or_insert_with<F: FnOnce() -> T>(self, f: F) -> NodeWriteGuard<'op, 'op, 't, T, C> {
+        match self {
+            Entry::Occupied(node) => node,
+            Entry::Vacant(vacant) => vacant.insert(f()),
+        }
+    }
+}
+impl<'op, 't: 'op, T: Debug, C: FixedSizeArray<ChildId>> Debug for Entry<'op, 't, T, C> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &Entry::Occupied(ref node) => f.debug_tuple("Occupied").field(node).finish(),
+            &Entry::Vacant(ref vacant) => f.debug_tuple("Vacant").field(vacant).finish(),
+        }
+    }
+}
+
+/// A position in the tree where there is no node, which is either a missing child, or the root
+/// of an empty tree.
+pub struct VacantChild<'op, 't: 'op, T: 'op, C: FixedSizeArray<ChildId> + 'op> {
+    op: &'op TreeOperation<'t, T, C>,
+    parent: Option<usize>,
+    branch: usize,
+}
+impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> VacantChild<'op, 't, T, C> {
+    /// The branch of the parent node which is vacant, or none if the vacant position is the root.
+    pub fn branch(&self) -> Option<usize> {
+        self.parent.map(|_| self.branch)
+    }
+
+    /// Place an element at the vacant position, and write to the new node.
+    pub fn insert(self, elem: T) -> NodeWriteGuard<'op, 'op, 't, T, C> {
+        match self.parent {
+            Some(parent_index) => {
+                let mut children = ChildWriteGuard {
+                    op: self.op,
+                    index: parent_index,
+
+                    p1: PhantomData,
+                };
+                children.put_child_elem(self.branch, elem).unwrap();
+                children.into_child_write(self.branch).unwrap().unwrap()
+            },
+            None => unsafe {
+                let tree = &*self.op.tree;
+                let nodes = &mut *tree.nodes.get();
+                nodes.push(UnsafeCell::new(Node::Present {
+                    elem: UnsafeCell::new(elem),
+                    parent: Cell::new(ParentId::Root),
+                    children: UnsafeCell::new(new_child_array()),
+                }));
+                let root_index = nodes.len() - 1;
+                tree.push_shape(None);
+                tree.root.set(Some(root_index));
+                tree.mark_dirty(root_index);
+
+                NodeWriteGuard {
+                    op: self.op,
+                    index: root_index,
+
+                    p1: PhantomData,
+                }
+            },
+        }
+    }
+}
+impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> Debug for VacantChild<'op, 't, T, C> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("VacantChild")
+            .field("branch", &self.branch())
+            .finish()
+    }
+}
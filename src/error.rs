@@ -0,0 +1,45 @@
+//! The error type shared by fallible operations on trees.
+
+use NoParent;
+
+use std::fmt::{Display, Formatter};
+use std::fmt;
+#[cfg(feature = "std")]
+use std::error;
+
+/// The error type for fallible operations on trees and their guards.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// A branch index which does not exist in the branch factor.
+    InvalidBranchIndex(usize),
+    /// A branch which has no child, when the operation requires one.
+    ChildNotFound(usize),
+    /// A traversal upwards from a node which has no parent.
+    NoParent(NoParent),
+    /// An output array, for streaming all children, of the wrong length.
+    WrongChildrenNum {
+        expected_num: usize,
+        actual_num: usize,
+    },
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &Error::InvalidBranchIndex(branch) => write!(f, "invalid branch index {}", branch),
+            &Error::ChildNotFound(branch) => write!(f, "no child at branch {}", branch),
+            &Error::NoParent(NoParent::Root) => f.write_str("node is the root, and has no parent"),
+            &Error::NoParent(NoParent::Detached) => f.write_str("node is detached, and has no parent"),
+            &Error::WrongChildrenNum {
+                expected_num,
+                actual_num,
+            } => write!(f, "expected room for {} children, found {}", expected_num, actual_num),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl error::Error for Error {}
+impl From<NoParent> for Error {
+    fn from(no_parent: NoParent) -> Self {
+        Error::NoParent(no_parent)
+    }
+}
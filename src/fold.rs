@@ -0,0 +1,50 @@
+//! Bottom-up evaluation of subtrees.
+
+use {Tree, ChildId, NodeReadGuard};
+
+use core::array::FixedSizeArray;
+use std::vec::Vec;
+
+impl<'tree, T, C: FixedSizeArray<ChildId>> NodeReadGuard<'tree, T, C> {
+    /// Fold the subtree rooted at this node, bottom-up. The function is called once for each
+    /// node, in post-order, with its element and the results for each of its branches, which are
+    /// none where there is no child.
+    ///
+    /// This uses an explicit stack instead of recursion, so it cannot overflow the call stack on
+    /// deep trees.
+    pub fn fold<R, F: FnMut(&'tree T, &[Option<R>]) -> R>(&self, mut f: F) -> R {
+        // each frame is a node, and the position in results where its branch results begin
+        let mut stack = vec![(unsafe { NodeReadGuard::new(self.tree, self.index) }, 0)];
+        let mut results: Vec<Option<R>> = Vec::new();
+        loop {
+            let next = {
+                let &(ref node, start) = stack.last().unwrap();
+                node.child(results.len() - start)
+            };
+            match next {
+                Ok(Some(child)) => {
+                    let start = results.len();
+                    stack.push((child, start));
+                },
+                Ok(None) => results.push(None),
+                Err(_) => {
+                    // every branch has a result
+                    let (node, start) = stack.pop().unwrap();
+                    let result = f(node.elem, &results[start..]);
+                    results.truncate(start);
+                    if stack.is_empty() {
+                        return result;
+                    }
+                    results.push(Some(result));
+                },
+            };
+        }
+    }
+}
+
+impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
+    /// Fold the whole tree bottom-up, as in `NodeReadGuard::fold`, or none if the tree is empty.
+    pub fn fold<'tree, R, F: FnMut(&'tree T, &[Option<R>]) -> R>(&'tree self, f: F) -> Option<R> {
+        self.read_root().map(|root| root.fold(f))
+    }
+}
@@ -1,20 +1,69 @@
 #![feature(fixed_size_array)]
 #![feature(optin_builtin_traits)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "std")]
 extern crate core;
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+#[cfg(all(test, feature = "std"))]
+extern crate proptest;
+
+/// Without the standard library, `std` paths refer to this module instead, which re-exports the
+/// parts of `std` which bonzai uses from `core` and `alloc`.
+#[cfg(not(feature = "std"))]
+mod std {
+    pub use core::{borrow, cell, cmp, fmt, iter, marker, mem, num, ops, ptr, slice, str};
+    pub use alloc::{boxed, string, vec};
+}
 
 mod pinned_vec;
-#[cfg(test)]
+pub mod collections;
+mod augment;
+mod ancestry;
+mod transplant;
+mod mapping;
+mod diff;
+mod fold;
+mod retain;
+mod path;
+mod search;
+mod entry;
+mod child_array;
+mod display;
+mod dot;
+mod validate;
+mod error;
+mod address;
+#[cfg(any(test, feature = "fuzzing"))]
+#[doc(hidden)]
+pub mod model;
+#[cfg(all(test, feature = "std"))]
 mod test;
 
 use pinned_vec::PinnedVec;
+use std::vec::Vec;
+
+pub use augment::{Augment, Augmented};
+pub use ancestry::AncestorIndex;
+pub use diff::{DiffOp, Zip};
+pub use path::{BranchPath, PathArray, Overlap};
+pub use search::FindAll;
+pub use entry::{Entry, VacantChild};
+pub use child_array::ChildArray;
+pub use display::{DisplayTree, DisplayElem};
+pub use dot::DotOptions;
+pub use validate::Corruption;
+pub use error::Error;
+pub use address::NodeAddress;
 
 use core::array::FixedSizeArray;
 use std::cell::{UnsafeCell, Cell};
 use std::ops::{Deref, DerefMut, Drop};
 use std::marker::PhantomData;
 use std::ptr;
-use std::mem;
+use std::mem::{self, MaybeUninit};
 use std::fmt::{Debug, Formatter};
 use std::fmt;
 
@@ -37,6 +86,9 @@ pub trait ReadRoot<T, C: FixedSizeArray<ChildId>> {
 
 /// Types that allow a certain node index to be mutably accessed.
 pub trait GetElemMut<T> {
+    /// Mutably access the element at an index, or none if the index is out of range or refers to
+    /// garbage. A TreeOperation also gives none for a node which is not attached to the root, such
+    /// as a node of a detached subtree, or a descendant of garbage which is not yet collected.
     fn get_elem_mut(&mut self, index: NodeIndex) -> Option<&mut T>;
 }
 
@@ -51,11 +103,6 @@ impl Debug for ChildId {
     }
 }
 
-/// Error type for performing operations on a branch index that does not exist in the
-/// given branch factor.
-#[derive(Debug, Copy, Clone)]
-pub struct InvalidBranchIndex(pub usize);
-
 enum Node<T, C: FixedSizeArray<ChildId>> {
     Garbage {
         children: C,
@@ -122,18 +169,82 @@ enum ParentId {
     Garbage,
 }
 
+/// The cached subtree length and depth of a node, for trees which cache their shape.
+#[derive(Copy, Clone, Debug)]
+struct Shape {
+    subtree_len: usize,
+    depth: usize,
+}
+
 fn new_child_array<C: FixedSizeArray<ChildId>>() -> C {
     unsafe {
-        let mut children: C = mem::uninitialized();
-        for child_ref in children.as_mut_slice() {
-            ptr::write(child_ref, ChildId {
+        // C is always an array of ChildId, so it can be initialized element by element
+        let mut children: MaybeUninit<C> = MaybeUninit::uninit();
+        let first = children.as_mut_ptr() as *mut ChildId;
+        for i in 0..mem::size_of::<C>() / mem::size_of::<ChildId>() {
+            ptr::write(first.add(i), ChildId {
                 index: None
             });
         }
-        children
+        children.assume_init()
+    }
+}
+
+/// A pre-order walk with an explicit stack, so that deep trees cannot overflow the call stack.
+/// When an item is visited, a function pushes its children, which are then visited in the order
+/// they were pushed. The items are usually nodes, but can also be pairs of nodes from two trees.
+struct PreOrder<N, F> {
+    stack: Vec<N>,
+    children: F,
+    // the stack length before the children of the last visited item were pushed
+    skip_to: usize,
+}
+impl<N, F: FnMut(&N, &mut Vec<N>)> PreOrder<N, F> {
+    fn new(root: Option<N>, children: F) -> Self {
+        PreOrder {
+            stack: root.into_iter().collect(),
+            children,
+            skip_to: 0,
+        }
+    }
+
+    /// Do not visit the descendants of the last visited item.
+    fn skip_children(&mut self) {
+        self.stack.truncate(self.skip_to);
+    }
+}
+impl<N, F: FnMut(&N, &mut Vec<N>)> Iterator for PreOrder<N, F> {
+    type Item = N;
+
+    fn next(&mut self) -> Option<N> {
+        let item = self.stack.pop()?;
+        self.skip_to = self.stack.len();
+        (self.children)(&item, &mut self.stack);
+        // reverse the children, so that they are popped in the order they were pushed
+        self.stack[self.skip_to..].reverse();
+        Some(item)
     }
 }
 
+/// Walk the subtree rooted at a present node in pre-order, visiting children in branch order,
+/// and yielding the index and depth of each node, where the subtree root has a depth of 0.
+unsafe fn subtree_pre_order<'a, T, C: FixedSizeArray<ChildId>>(tree: &'a Tree<T, C>, root_index: usize)
+    -> PreOrder<(usize, usize), impl FnMut(&(usize, usize), &mut Vec<(usize, usize)>) + 'a> {
+    let nodes = &*tree.nodes.get();
+    PreOrder::new(Some((root_index, 0)), move |&(index, depth), stack| {
+        if let &Node::Present {
+            ref children,
+            ..
+        } = &*nodes[index].get() {
+            stack.extend((&*children.get()).as_slice().iter()
+                .filter_map(|child_id| child_id.index)
+                .map(|child_index| (child_index, depth + 1)));
+        } else {
+            unreachable!("pre-order walk visited garbage node");
+        }
+    })
+}
+
 /// A struct which borrows from the tree, and allows the debug printing of the tree's
 /// node vector, for debugging purposes.
 pub struct DebugNodes<'a, T, C: FixedSizeArray<ChildId>> {
@@ -163,6 +274,10 @@ pub struct Tree<T, C: FixedSizeArray<ChildId>> {
     nodes: UnsafeCell<PinnedVec<UnsafeCell<Node<T, C>>>>,
     root: Cell<Option<usize>>,
     garbage: UnsafeCell<Vec<usize>>,
+    dirty: UnsafeCell<Vec<usize>>,
+    augment: Option<fn(&mut Tree<T, C>)>,
+    shapes: UnsafeCell<Vec<Shape>>,
+    shape_cache: bool,
 }
 impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
     /// Create a new, empty tree.
@@ -171,6 +286,160 @@ impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
             nodes: UnsafeCell::new(PinnedVec::new(EXTENSION_SIZE)),
             root: Cell::new(None),
             garbage: UnsafeCell::new(Vec::new()),
+            dirty: UnsafeCell::new(Vec::new()),
+            augment: None,
+            shapes: UnsafeCell::new(Vec::new()),
+            shape_cache: false,
+        }
+    }
+
+    /// Begin caching the subtree length and depth of every node, so that they can be queried in
+    /// constant time. This is an O(N) operation.
+    ///
+    /// While the shape is cached, attaching or detaching a subtree is O(M + D), where M is the
+    /// number of nodes in that subtree, and D is the depth at which it is attached or detached.
+    pub fn enable_shape_cache(&mut self) {
+        if self.shape_cache {
+            return;
+        }
+
+        // after garbage collection, every node is part of the main tree
+        self.garbage_collect();
+
+        unsafe {
+            let nodes = &*self.nodes.get();
+            let shapes = &mut *self.shapes.get();
+            shapes.clear();
+            shapes.resize(nodes.len(), Shape {
+                subtree_len: 1,
+                depth: 0,
+            });
+
+            // compute depths in pre-order
+            let mut order = Vec::with_capacity(nodes.len());
+            let mut stack: Vec<usize> = self.root.get().into_iter().collect();
+            while let Some(index) = stack.pop() {
+                order.push(index);
+                if let &Node::Present {
+                    ref children,
+                    ..
+                } = &*nodes[index].get() {
+                    for child_index in (&*children.get()).as_slice().iter().filter_map(|child_id| child_id.index) {
+                        shapes[child_index].depth = shapes[index].depth + 1;
+                        stack.push(child_index);
+                    }
+                }
+            }
+
+            // compute subtree lengths in reverse pre-order, which visits children before parents
+            for &index in order.iter().rev() {
+                if let &Node::Present {
+                    ref parent,
+                    ..
+                } = &*nodes[index].get() {
+                    if let ParentId::Some {
+                        parent_index,
+                        ..
+                    } = parent.get() {
+                        shapes[parent_index].subtree_len += shapes[index].subtree_len;
+                    }
+                }
+            }
+        }
+
+        self.shape_cache = true;
+    }
+
+    /// Cache the shape of a node which was just pushed to the node vec, given its parent.
+    unsafe fn push_shape(&self, parent: Option<usize>) {
+        if self.shape_cache {
+            let shapes = &mut *self.shapes.get();
+            let depth = parent.map(|parent_index| shapes[parent_index].depth + 1).unwrap_or(0);
+            shapes.push(Shape {
+                subtree_len: 1,
+                depth,
+            });
+        }
+    }
+
+    /// The cached subtree length of a child, or 0 if there is no child or no shape cache.
+    unsafe fn cached_subtree_len(&self, child_id: ChildId) -> usize {
+        match child_id.index {
+            Some(child_index) if self.shape_cache => (&*self.shapes.get())[child_index].subtree_len,
+            _ => 0,
+        }
+    }
+
+    /// Add to the cached subtree length of a node and all its ancestors.
+    unsafe fn adjust_subtree_lens(&self, index: usize, delta: isize) {
+        if !self.shape_cache || delta == 0 {
+            return;
+        }
+        let nodes = &*self.nodes.get();
+        let shapes = &mut *self.shapes.get();
+        let mut curr = index;
+        loop {
+            shapes[curr].subtree_len = (shapes[curr].subtree_len as isize + delta) as usize;
+            match &*nodes[curr].get() {
+                &Node::Present {
+                    ref parent,
+                    ..
+                } => match parent.get() {
+                    ParentId::Some {
+                        parent_index,
+                        ..
+                    } => curr = parent_index,
+                    _ => break,
+                },
+                &Node::Garbage { .. } => unreachable!("adjusting subtree length of garbage"),
+            };
+        }
+    }
+
+    /// Set the cached depth of a node, shifting the cached depths of its descendants to match.
+    unsafe fn set_subtree_depth(&self, index: usize, depth: usize) {
+        if !self.shape_cache {
+            return;
+        }
+        let nodes = &*self.nodes.get();
+        let shapes = &mut *self.shapes.get();
+        let offset = depth as isize - shapes[index].depth as isize;
+        if offset == 0 {
+            return;
+        }
+        let mut stack = vec![index];
+        while let Some(curr) = stack.pop() {
+            shapes[curr].depth = (shapes[curr].depth as isize + offset) as usize;
+            if let &Node::Present {
+                ref children,
+                ..
+            } = &*nodes[curr].get() {
+                stack.extend((&*children.get()).as_slice().iter().filter_map(|child_id| child_id.index));
+            }
+        }
+    }
+
+    /// Update the shape cache for a subtree which was just detached from a parent.
+    unsafe fn shape_detached(&self, index: usize, former_parent: usize) {
+        if self.shape_cache {
+            let subtree_len = (&*self.shapes.get())[index].subtree_len;
+            self.adjust_subtree_lens(former_parent, -(subtree_len as isize));
+            self.set_subtree_depth(index, 0);
+        }
+    }
+
+    /// Record that a node's element or children may have changed, if this tree maintains aggregates.
+    unsafe fn mark_dirty(&self, index: usize) {
+        if self.augment.is_some() {
+            (&mut *self.dirty.get()).push(index);
+        }
+    }
+
+    /// Bring the subtree aggregates of an augmented tree up to date. This is automatically done
+    /// whenever a TreeOperation is dropped. For trees which are not augmented, this does nothing.
+    pub fn update_aggregates(&mut self) {
+        if let Some(augment) = self.augment {
+            augment(self);
         }
     }
 
@@ -197,6 +466,17 @@ impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
             })
     }
 
+    /// Begin read-traversing from some arbitrary node in the tree, if it is present and attached
+    /// to the root, as in `NodeAddress` for `NodeIndex`. A stale index gives none.
+    ///
+    /// Instead of using this method, use the traverse_read_from! macro.
+    pub fn traverse_read_from<'tree>(&'tree self, index: NodeIndex) -> Option<TreeReadTraverser<'tree, T, C>> {
+        index.resolve(self)
+            .map(|node| unsafe {
+                TreeReadTraverser::new(self, node.index)
+            })
+    }
+
     /// Begin an operation which can mutate the tree.
     pub fn operation<'tree>(&'tree mut self) -> TreeOperation<'tree, T, C> {
         TreeOperation {
@@ -218,6 +498,9 @@ impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
     ///
     /// This will cause all non-dropped garbage nodes to be dropped.
     pub fn garbage_collect(&mut self) {
+        // relocation would invalidate the dirty node indices
+        self.update_aggregates();
+
         unsafe {
             let garbage_vec = &mut*self.garbage.get();
             let nodes = &mut*self.nodes.get();
@@ -244,29 +527,43 @@ impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
                 });
 
                 let removed_node = nodes.swap_remove(garbage_index);
+                if self.shape_cache {
+                    (&mut *self.shapes.get()).swap_remove(garbage_index);
+                }
                 let relocated_new_index = garbage_index;
+                let relocated_old_index = nodes.len();
 
                 // mark the removed node's children for deletion
-                if let Node::Garbage {
-                    children
-                } = removed_node.into_inner() {
-                    for &child_id in children.as_slice() {
-                        if let ChildId {
-                            index: Some(child_index)
-                        } = child_id {
-                            // we've found a node to mark as garbage
+                let removed_children = match removed_node.into_inner() {
+                    Node::Garbage {
+                        children
+                    } => children,
+                    Node::Present {
+                        children,
+                        ..
+                    } => children.into_inner(),
+                };
+                for &child_id in removed_children.as_slice() {
+                    if let ChildId {
+                        index: Some(child_index)
+                    } = child_id {
+                        // we've found a node to mark as garbage
+                        // if it was just relocated, it will be queued when it's reattached below
+                        let child_index = if child_index == relocated_old_index {
+                            relocated_new_index
+                        } else {
                             garbage_vec.push(child_index);
-                            if let &Node::Present {
-                                ref parent,
-                                ..
-                            } = &*nodes[child_index].get() {
-                                parent.set(ParentId::Garbage);
-                            }
+                            child_index
+                        };
+                        if let &Node::Present {
+                            ref parent,
+                            ..
+                        } = &*nodes[child_index].get() {
+                            parent.set(ParentId::Garbage);
                         }
                     }
-                } // else, it means we got here because the parent was marked
+                }
 
-                let relocated_old_index = nodes.len();
                 if relocated_new_index == relocated_old_index {
                     // we don't need to perform reattachment if we removed the last node in the vec
                     // that would actually cause a panic
@@ -275,9 +572,13 @@ impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
 
                 let relocated_node = &mut*(&nodes[relocated_new_index]).get();
 
-                match relocated_node {
-                    &mut Node::Garbage { .. } => {
+                let relocated_children = match relocated_node {
+                    &mut Node::Garbage {
+                        ref children
+                    } => {
+                        // the stale index will be skipped, so queue it again at the new index
                         garbage_vec.push(relocated_new_index);
+                        children.as_slice()
                     }
                     &mut Node::Present {
                         ref mut parent,
@@ -290,9 +591,9 @@ impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
                                 parent_index,
                                 this_branch,
                             } => {
-                                let parent_node = &*(&nodes[parent_index]).get();
+                                let parent_node = &mut*(&nodes[parent_index]).get();
                                 match parent_node {
-                                    &Node::Present {
+                                    &mut Node::Present {
                                         ref children,
                                         ..
                                     } => {
@@ -300,49 +601,59 @@ impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
                                             index: Some(relocated_new_index),
                                         };
                                     },
-                                    &Node::Garbage { .. } => {
-                                        unreachable!("node parent is garbage at garbage collection time");
+                                    &mut Node::Garbage {
+                                        ref mut children
+                                    } => {
+                                        // the parent hasn't been collected yet, and must still find this node
+                                        children.as_mut_slice()[this_branch] = ChildId {
+                                            index: Some(relocated_new_index),
+                                        };
                                     }
                                 }
                             },
                             ParentId::Root => {
                                 self.root.set(Some(relocated_new_index));
                             },
-                            ParentId::Garbage => (),
+                            ParentId::Garbage => {
+                                // the stale index will be skipped, so queue it again at the new index
+                                garbage_vec.push(relocated_new_index);
+                            },
                             ParentId::Detached => {
                                 unreachable!("found detached node on garbage collection sweep");
                             }
                         };
 
-                        // reconnect children
-                        for (b, child_id) in (&*children.get()).as_slice().iter().enumerate() {
-                            if let &ChildId {
-                                index: Some(child_index)
-                            } = child_id {
+                        (&*children.get()).as_slice()
+                    }
+                };
 
-                                let child_node = &*(&nodes[child_index]).get();
-                                match child_node {
-                                    &Node::Present {
-                                        ref parent,
-                                        ..
-                                    } => {
-                                        debug_assert_eq!(parent.get(), ParentId::Some {
-                                            parent_index: relocated_old_index,
-                                            this_branch: b,
-                                        });
-                                        parent.set(ParentId::Some {
-                                            parent_index: relocated_new_index,
-                                            this_branch: b,
-                                        });
-                                    }
-                                    &Node::Garbage { .. } => {
-                                        unreachable!("node child is garbage at garbage collection time");
-                                    }
-                                };
+                // reconnect children
+                for (b, child_id) in relocated_children.iter().enumerate() {
+                    if let &ChildId {
+                        index: Some(child_index)
+                    } = child_id {
+
+                        let child_node = &*(&nodes[child_index]).get();
+                        match child_node {
+                            &Node::Present {
+                                ref parent,
+                                ..
+                            } => {
+                                debug_assert_eq!(parent.get(), ParentId::Some {
+                                    parent_index: relocated_old_index,
+                                    this_branch: b,
+                                });
+                                parent.set(ParentId::Some {
+                                    parent_index: relocated_new_index,
+                                    this_branch: b,
+                                });
                             }
-                        }
+                            &Node::Garbage { .. } => {
+                                unreachable!("node child is garbage at garbage collection time");
+                            }
+                        };
                     }
-                };
+                }
             }
         }
     }
@@ -356,6 +667,8 @@ impl<T, C: FixedSizeArray<ChildId>> ReadRoot<T, C> for Tree<T, C> {
     }
 }
 impl<T, C: FixedSizeArray<ChildId>> GetElemMut<T> for Tree<T, C> {
+    /// In an augmented tree, the node is marked dirty, but its aggregate and those of its ancestors
+    /// are not recomputed until `update_aggregates` is called, or the next operation is dropped.
     fn get_elem_mut(&mut self, index: NodeIndex) -> Option<&mut T> {
         unsafe {
             if index.index < (&*self.nodes.get()).len() {
@@ -363,7 +676,10 @@ impl<T, C: FixedSizeArray<ChildId>> GetElemMut<T> for Tree<T, C> {
                     &Node::Present {
                         ref elem,
                         ..
-                    } => Some(&mut *elem.get()),
+                    } => {
+                        self.mark_dirty(index.index);
+                        Some(&mut *elem.get())
+                    },
                     &Node::Garbage { .. } => None
                 }
             } else {
@@ -431,9 +747,9 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
             })
     }
 
-    unsafe fn delete_root(&mut self, nodes_vec: &mut PinnedVec<UnsafeCell<Node<T, C>>>) -> bool {
+    unsafe fn delete_root(&mut self, nodes_vec: &PinnedVec<UnsafeCell<Node<T, C>>>) -> bool {
         if let Some(former_root_index) = self.tree.root.get() {
-            (&mut*nodes_vec[former_root_index].get()).take_elem_become_garbage();
+            (&mut *(&nodes_vec[former_root_index]).get()).take_elem_become_garbage();
             (&mut*self.tree.garbage.get()).push(former_root_index);
             true
         } else {
@@ -455,17 +771,18 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
                 children: UnsafeCell::new(child_children),
             };
 
-            let nodes_vec = &mut*self.tree.nodes.get();
-
             // insert it into the nodes vector, get the index
-            nodes_vec.push(UnsafeCell::new(child_node));
+            (&mut*self.tree.nodes.get()).push(UnsafeCell::new(child_node));
+            let nodes_vec = &*self.tree.nodes.get();
             let child_index = nodes_vec.len() - 1;
+            self.tree.push_shape(None);
 
             // mark any existing root as garbage
             let deleted = self.delete_root(nodes_vec);
 
             // attach the root
             self.tree.root.set(Some(child_index));
+            self.tree.mark_dirty(child_index);
 
             // done
             deleted
@@ -476,7 +793,7 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
     /// root was overridden.
     pub fn put_root_tree<'s>(&mut self, mut subtree: NodeOwnedGuard<'s, 'tree, T, C>) -> bool {
         unsafe {
-            let nodes_vec = &mut*self.tree.nodes.get();
+            let nodes_vec = &*self.tree.nodes.get();
 
             // mark any existing root as garbage
             let deleted = self.delete_root(nodes_vec);
@@ -518,7 +835,7 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
                     attempted_to_put: subtree,
                 })
             } else {
-                let nodes_vec = &mut *self.tree.nodes.get();
+                let nodes_vec = &*self.tree.nodes.get();
 
                 // attach the root
                 self.tree.root.set(Some(subtree.index));
@@ -545,7 +862,7 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
     }
 
     /// Begin write-traversing from the root of the tree, if the root exists.
-    pub fn traverse_root<'s>(&'s mut self) -> Option<TreeWriteTraverser<'tree, 's, T, C>> {
+    pub fn traverse_root<'s>(&'s mut self) -> Option<TreeWriteTraverser<'s, 'tree, T, C>> {
         self.tree.root.get()
             .map(move |root_index| TreeWriteTraverser {
                 op: self,
@@ -553,18 +870,17 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
             })
     }
 
-    /// Begin write-traversing from some arbitrary node in the tree.
+    /// Begin write-traversing from some arbitrary node in the tree, if it is present and attached
+    /// to the root, as in `NodeAddress` for `NodeIndex`. A stale index, such as one kept from
+    /// before its node was removed, gives none.
     ///
     /// Instead of using this method, use the traverse_from! macro.
     pub fn traverse_from<'s>(&'s mut self, index: NodeIndex) -> Option<TreeWriteTraverser<'s, 'tree, T, C>> {
-        if index.index < unsafe { (&*self.tree.nodes.get()).len() } {
-            Some(TreeWriteTraverser {
+        index.resolve(self.tree)
+            .map(move |node| TreeWriteTraverser {
                 op: self,
-                index: Cell::new(index.index),
+                index: Cell::new(node.index),
             })
-        } else {
-            None
-        }
     }
 
     /// Create a new detached subtree.
@@ -582,6 +898,8 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
             // add it to the vec
             node_vec.push(UnsafeCell::new(node));
             let node_index = node_vec.len() - 1;
+            self.tree.push_shape(None);
+            self.tree.mark_dirty(node_index);
 
             // create the guard
             NodeOwnedGuard {
@@ -602,6 +920,12 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
     pub fn read_root<'s>(&'s self) -> Option<NodeReadGuard<'s, T, C>> {
         self.tree.read_root()
     }
+
+    /// Bring the subtree aggregates of an augmented tree up to date, without waiting for this
+    /// operation to end.
+    pub fn update_aggregates(&mut self) {
+        self.tree.update_aggregates();
+    }
 }
 impl<'tree, T, C: FixedSizeArray<ChildId>> ReadRoot<T, C> for TreeOperation<'tree, T, C> {
     fn read_root<'s>(&'s self) -> Option<NodeReadGuard<'s, T, C>> {
@@ -642,6 +966,7 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> NodeWriteGuard<'op, 'no
             ..
         } = &*(&*self.op.tree.nodes.get())[self.index].get() {
             //let elem: &'this mut T = &mut*elem.get();
+            self.op.tree.mark_dirty(self.index);
             let elem = &mut*elem.get();
             //let child_guard: ChildWriteGuard<'tree, 'this, T, C> = ChildWriteGuard {
             let child_guard = ChildWriteGuard {
@@ -709,6 +1034,8 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> NodeWriteGuard<'op, 'no
                         (&mut*children.get()).as_mut_slice()[this_branch] = ChildId {
                             index: None
                         };
+                        self.op.tree.mark_dirty(parent_index);
+                        self.op.tree.shape_detached(self.index, parent_index);
                     } else {
                         unreachable!("write guard parent index points to garbage");
                     }
@@ -806,6 +1133,7 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> NodeOwnedGuard<'op, 't, T, C>
                 ref elem,
                 ..
             } = &*(&*self.op.tree.nodes.get())[self.index].get() {
+                self.op.tree.mark_dirty(self.index);
                 let elem = &mut*elem.get();
                 let child_guard = ChildWriteGuard {
                     op: self.op,
@@ -863,6 +1191,8 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> Drop for NodeOwnedGuard<'op, '
         }
     }
 }
+impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> !Send for NodeOwnedGuard<'op, 't, T, C> {}
+impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> !Sync for NodeOwnedGuard<'op, 't, T, C> {}
 impl<'op, 't: 'op, T: Debug, C: FixedSizeArray<ChildId>> Debug for NodeOwnedGuard<'op, 't, T, C> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
         self.into_read_guard().fmt(f)
@@ -888,13 +1218,6 @@ for &'s mut NodeOwnedGuard<'op, 't, T, C> {
     }
 }
 
-/// An error type for streaming all children, when the output array is of the wrong size.
-#[derive(Debug)]
-pub struct WrongChildrenNum {
-    pub expected_num: usize,
-    pub actual_num: usize,
-}
-
 /// Mutable access to a node's children.
 ///
 /// A `ChildWriteGuard` represents mutable access to a node's children. It can be borrowed from both a
@@ -923,9 +1246,9 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
     }
 
     unsafe fn make_child_write_guard<'n>(&mut self, branch: usize)
-        -> Result<Option<NodeWriteGuard<'op, 'n, 't, T, C>>, InvalidBranchIndex> {
+        -> Result<Option<NodeWriteGuard<'op, 'n, 't, T, C>>, Error> {
         self.children().as_slice().get(branch)
-            .ok_or(InvalidBranchIndex(branch))
+            .ok_or(Error::InvalidBranchIndex(branch))
             .map(|child_id| child_id.index)
             .map(|child_index| child_index
                 .map(move |child_index| NodeWriteGuard {
@@ -938,7 +1261,7 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
 
     /// Borrow a write guard for a certain child, if that child exists.
     pub fn borrow_child_write<'s>(&'s mut self, branch: usize)
-        -> Result<Option<NodeWriteGuard<'op, 's, 't, T, C>>, InvalidBranchIndex> {
+        -> Result<Option<NodeWriteGuard<'op, 's, 't, T, C>>, Error> {
         unsafe {
             self.make_child_write_guard(branch)
         }
@@ -946,7 +1269,7 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
 
     /// Turn into the write guard for a certain child, if that child exists.
     pub fn into_child_write(mut self, branch: usize)
-        -> Result<Option<NodeWriteGuard<'op, 'node, 't, T, C>>, InvalidBranchIndex> {
+        -> Result<Option<NodeWriteGuard<'op, 'node, 't, T, C>>, Error> {
         unsafe {
             self.make_child_write_guard(branch)
         }
@@ -955,21 +1278,16 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
     /// Stream all child write guards into an array, borrowing from self.
     pub fn borrow_all_children<'s>(&'s mut self,
                                    out: &mut [Option<NodeWriteGuard<'op, 's, 't, T, C>>])
-        -> Result<(), WrongChildrenNum> {
+        -> Result<(), Error> {
         unsafe {
-            let branch_factor = {
-                let array: C = mem::uninitialized();
-                let size = array.as_slice().len();
-                mem::forget(array);
-                size
-            };
+            let branch_factor = new_child_array::<C>().as_slice().len();
             if branch_factor == out.len() {
                 for b in 0..branch_factor {
                     out[b] = self.make_child_write_guard(b).unwrap();
                 }
                 Ok(())
             } else {
-                Err(WrongChildrenNum {
+                Err(Error::WrongChildrenNum {
                     expected_num: branch_factor,
                     actual_num: out.len()
                 })
@@ -979,21 +1297,16 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
 
     /// Stream all child write guards into an array, consuming from self.
     pub fn into_all_children(mut self, out: &mut [Option<NodeWriteGuard<'op, 'node, 't, T, C>>])
-        -> Result<(), WrongChildrenNum> {
+        -> Result<(), Error> {
         unsafe {
-            let branch_factor = {
-                let array: C = mem::uninitialized();
-                let size = array.as_slice().len();
-                mem::forget(array);
-                size
-            };
+            let branch_factor = new_child_array::<C>().as_slice().len();
             if branch_factor == out.len() {
                 for b in 0..branch_factor {
                     out[b] = self.make_child_write_guard(b).unwrap();
                 }
                 Ok(())
             } else {
-                Err(WrongChildrenNum {
+                Err(Error::WrongChildrenNum {
                     expected_num: branch_factor,
                     actual_num: out.len()
                 })
@@ -1002,10 +1315,10 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
     }
 
     /// Detach a child, turning it into a detached subtree, if that child exists.
-    pub fn take_child(&mut self, branch: usize) -> Result<Option<NodeOwnedGuard<'op, 't, T, C>>, InvalidBranchIndex> {
+    pub fn take_child(&mut self, branch: usize) -> Result<Option<NodeOwnedGuard<'op, 't, T, C>>, Error> {
 
         self.children().as_slice().get(branch)
-            .ok_or(InvalidBranchIndex(branch))
+            .ok_or(Error::InvalidBranchIndex(branch))
             .map(|child_id| child_id.index)
             .map(|child_index| child_index
                 .map(move |child_index| {
@@ -1025,6 +1338,10 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
                     self.children().as_mut_slice()[branch] = ChildId {
                         index: None
                     };
+                    unsafe {
+                        self.op.tree.mark_dirty(self.index);
+                        self.op.tree.shape_detached(child_index, self.index);
+                    }
 
                     // create the guard
                     NodeOwnedGuard {
@@ -1037,12 +1354,12 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
     }
 
     unsafe fn delete_child(&mut self,
-                           nodes_vec: &mut PinnedVec<UnsafeCell<Node<T, C>>>,
+                           nodes_vec: &PinnedVec<UnsafeCell<Node<T, C>>>,
                            branch: usize) -> bool {
         if let ChildId {
             index: Some(former_child_index)
         } = self.children().as_slice()[branch] {
-            (&mut*nodes_vec[former_child_index].get()).take_elem_become_garbage();
+            (&mut *(&nodes_vec[former_child_index]).get()).take_elem_become_garbage();
             (&mut*self.op.tree.garbage.get()).push(former_child_index);
             true
         } else {
@@ -1051,11 +1368,11 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
     }
 
     /// Put an element as a certain child, returning whether any existing child was overridden.
-    pub fn put_child_elem(&mut self, branch: usize, elem: T) -> Result<bool, InvalidBranchIndex> {
+    pub fn put_child_elem(&mut self, branch: usize, elem: T) -> Result<bool, Error> {
         unsafe {
             // short-circuit if the branch is invalid
             if branch >= self.children().as_slice().len() {
-                return Err(InvalidBranchIndex(branch));
+                return Err(Error::InvalidBranchIndex(branch));
             }
 
             // unsafely create the new children array
@@ -1071,19 +1388,22 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
                 children: UnsafeCell::new(child_children)
             };
 
-            let nodes_vec = &mut*self.op.tree.nodes.get();
-
             // insert it into the nodes vector, get the index
-            nodes_vec.push(UnsafeCell::new(child_node));
+            (&mut*self.op.tree.nodes.get()).push(UnsafeCell::new(child_node));
+            let nodes_vec = &*self.op.tree.nodes.get();
             let child_index = nodes_vec.len() - 1;
+            self.op.tree.push_shape(Some(self.index));
 
             // mark any existing child as garbage
+            let replaced_len = self.op.tree.cached_subtree_len(self.children().as_slice()[branch]);
             let deleted = self.delete_child(nodes_vec, branch);
+            self.op.tree.adjust_subtree_lens(self.index, 1 - replaced_len as isize);
 
             // attach the child
             self.children().as_mut_slice()[branch] = ChildId {
                 index: Some(child_index)
             };
+            self.op.tree.mark_dirty(child_index);
 
             // done
             Ok(deleted)
@@ -1092,22 +1412,24 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
 
     /// Attach a detached subtree as a certain child, returning whether any existing child was overridden.
     pub fn put_child_tree(&mut self, branch: usize, mut subtree: NodeOwnedGuard<'op, 't, T, C>)
-        -> Result<bool, InvalidBranchIndex> {
+        -> Result<bool, Error> {
         unsafe {
             // short-circuit if the branch is invalid
             if branch >= self.children().as_slice().len() {
-                return Err(InvalidBranchIndex(branch));
+                return Err(Error::InvalidBranchIndex(branch));
             }
 
-            let nodes_vec = &mut*self.op.tree.nodes.get();
+            let nodes_vec = &*self.op.tree.nodes.get();
 
             // mark any existing child as garbage
+            let replaced_len = self.op.tree.cached_subtree_len(self.children().as_slice()[branch]);
             let deleted = self.delete_child(nodes_vec, branch);
 
             // attach the child
             self.children().as_mut_slice()[branch] = ChildId {
                 index: Some(subtree.index),
             };
+            self.op.tree.mark_dirty(self.index);
 
             // attach the parent
             if let &Node::Present {
@@ -1123,6 +1445,16 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
                 unreachable!("put child tree references garbage");
             }
 
+            // update the shape cache
+            if self.op.tree.shape_cache {
+                let parent_depth = (&*self.op.tree.shapes.get())[self.index].depth;
+                self.op.tree.set_subtree_depth(subtree.index, parent_depth + 1);
+                let subtree_len = self.op.tree.cached_subtree_len(ChildId {
+                    index: Some(subtree.index)
+                });
+                self.op.tree.adjust_subtree_lens(self.index, subtree_len as isize - replaced_len as isize);
+            }
+
             // drop the NodeOwnedGuard without triggering it to mark the node as garbage
             subtree.reattached = true;
             mem::drop(subtree);
@@ -1135,12 +1467,8 @@ impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'n
 impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> !Send for ChildWriteGuard<'op, 'node, 't, T, C> {}
 impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> !Sync for ChildWriteGuard<'op, 'node, 't, T, C> {}
 
-/// Error type for attempting to detach a child that doesn't exist.
-#[derive(Debug)]
-pub struct ChildNotFound(pub usize);
-
-/// Error type for trying to traverse upwards when enable.
-#[derive(Debug)]
+/// The reason that a node has no parent to traverse upwards to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum NoParent {
     Root,
     Detached
@@ -1192,7 +1520,7 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
     }
 
     /// Attempt to point this traverser to the parent.
-    pub fn seek_parent(&self) -> Result<(), NoParent> {
+    pub fn seek_parent(&self) -> Result<(), Error> {
         unsafe {
             if let &mut Node::Present {
                 ref parent,
@@ -1206,8 +1534,8 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
                         self.index.set(parent_index);
                         Ok(())
                     },
-                    ParentId::Root => Err(NoParent::Root),
-                    ParentId::Detached => Err(NoParent::Detached),
+                    ParentId::Root => Err(Error::NoParent(NoParent::Root)),
+                    ParentId::Detached => Err(Error::NoParent(NoParent::Detached)),
                     ParentId::Garbage => unreachable!("garbage parent node encountered outside of GC"),
                 }
             } else {
@@ -1217,7 +1545,7 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
     }
 
     /// Does the given child exist.
-    pub fn has_child(&self, branch: usize) -> Result<bool, InvalidBranchIndex> {
+    pub fn has_child(&self, branch: usize) -> Result<bool, Error> {
         unsafe {
             if let &mut Node::Present {
                 ref children,
@@ -1225,7 +1553,7 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
             } = self.access_node_ref() {
                 (&*children.get()).as_slice()
                     .get(branch)
-                    .ok_or(InvalidBranchIndex(branch))
+                    .ok_or(Error::InvalidBranchIndex(branch))
                     .map(|child_id| child_id.index.is_some())
             } else {
                 unreachable!("tree write traverser points to garbage node")
@@ -1234,7 +1562,7 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
     }
 
     /// Attempt to point this traverser to the given child.
-    pub fn seek_child(&self, branch: usize) -> Result<Result<(), ChildNotFound>, InvalidBranchIndex> {
+    pub fn seek_child(&self, branch: usize) -> Result<(), Error> {
         unsafe {
             if let &mut Node::Present {
                 ref children,
@@ -1242,14 +1570,14 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
             } = self.access_node_ref() {
                 (&*children.get()).as_slice()
                     .get(branch)
-                    .ok_or(InvalidBranchIndex(branch))
-                    .map(|child_id| match child_id.index {
+                    .ok_or(Error::InvalidBranchIndex(branch))
+                    .and_then(|child_id| match child_id.index {
                         Some(child_index) => {
                             // acquired child index
                             self.index.set(child_index);
                             Ok(())
                         },
-                        None => Err(ChildNotFound(branch))
+                        None => Err(Error::ChildNotFound(branch))
                     })
             } else {
                 unreachable!("tree write traverser points to garbage node")
@@ -1286,6 +1614,8 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
                         (&mut*children.get()).as_mut_slice()[this_branch] = ChildId {
                             index: None
                         };
+                        self.op.tree.mark_dirty(parent_index);
+                        self.op.tree.shape_detached(self.index.get(), parent_index);
                     } else {
                         unreachable!("tree write traverser parent is garbage");
                     }
@@ -1309,23 +1639,25 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
 
     /// Attempt to detach a child node, producing a detached subtree.
     pub fn detach_child<'s>(&'s self, branch: usize)
-        -> Result<Result<NodeOwnedGuard<'op, 't, T, C>, ChildNotFound>, InvalidBranchIndex> {
+        -> Result<NodeOwnedGuard<'op, 't, T, C>, Error> {
         unsafe {
             if let &mut Node::Present {
                 ref children,
                 ..
             } = self.access_node_ref() {
-                let mut children_slice = (&mut*children.get()).as_mut_slice();
+                let children_slice = (&mut*children.get()).as_mut_slice();
                 children_slice
                     .get(branch).cloned()
-                    .ok_or(InvalidBranchIndex(branch))
-                    .map(|child_id| match child_id.index {
+                    .ok_or(Error::InvalidBranchIndex(branch))
+                    .and_then(|child_id| match child_id.index {
                         Some(child_index) => {
                             // acquired child index
                             // detach the child
                             children_slice[branch] = ChildId {
                                 index: None
                             };
+                            self.op.tree.mark_dirty(self.index.get());
+                            self.op.tree.shape_detached(child_index, self.index.get());
 
                             // detach the parent
                             if let &Node::Present {
@@ -1344,7 +1676,7 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
                                 reattached: false
                             })
                         },
-                        None => Err(ChildNotFound(branch))
+                        None => Err(Error::ChildNotFound(branch))
                     })
             } else {
                 unreachable!("tree write traverser points to garbage node")
@@ -1352,6 +1684,150 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
         }
     }
 
+    /// Rotate the child at `branch` up into the position of the pointed-at node, making the
+    /// pointed-at node the child of the promoted node at `swing_branch`. The subtree which was
+    /// previously at the promoted node's `swing_branch` is moved to the demoted node's `branch`.
+    /// Afterwards, this traverser points to the promoted node, which occupies the original position.
+    ///
+    /// This only relinks the existing nodes in place, and performs no allocation. In a binary tree,
+    /// `rotate_child_up(1, 0)` is a left rotation, and `rotate_child_up(0, 1)` is a right rotation.
+    pub fn rotate_child_up(&self, branch: usize, swing_branch: usize)
+        -> Result<(), Error> {
+        unsafe {
+            let nodes = &*self.op.tree.nodes.get();
+            let this_index = self.index.get();
+
+            let (this_parent, this_children) = match &*nodes[this_index].get() {
+                &Node::Present {
+                    ref parent,
+                    ref children,
+                    ..
+                } => (parent, &mut *children.get()),
+                &Node::Garbage { .. } => unreachable!("tree write traverser points to garbage node"),
+            };
+
+            // short-circuit if either branch is invalid
+            let branch_factor = this_children.as_slice().len();
+            if branch >= branch_factor {
+                return Err(Error::InvalidBranchIndex(branch));
+            }
+            if swing_branch >= branch_factor {
+                return Err(Error::InvalidBranchIndex(swing_branch));
+            }
+
+            // find the child to promote
+            let child_index = match this_children.as_slice()[branch].index {
+                Some(child_index) => child_index,
+                None => return Err(Error::ChildNotFound(branch)),
+            };
+            let (child_parent, child_children) = match &*nodes[child_index].get() {
+                &Node::Present {
+                    ref parent,
+                    ref children,
+                    ..
+                } => (parent, &mut *children.get()),
+                &Node::Garbage { .. } => unreachable!("child index points to garbage"),
+            };
+
+            // move the promoted node's swing subtree beneath this node
+            let swung = child_children.as_slice()[swing_branch];
+            this_children.as_mut_slice()[branch] = swung;
+            if let Some(swung_index) = swung.index {
+                if let &Node::Present {
+                    ref parent,
+                    ..
+                } = &*nodes[swung_index].get() {
+                    parent.set(ParentId::Some {
+                        parent_index: this_index,
+                        this_branch: branch,
+                    });
+                } else {
+                    unreachable!("child index points to garbage");
+                }
+            }
+
+            // attach the promoted node to whatever was above this node
+            let above = this_parent.get();
+            match above {
+                ParentId::Some {
+                    parent_index,
+                    this_branch,
+                } => {
+                    if let &Node::Present {
+                        ref children,
+                        ..
+                    } = &*nodes[parent_index].get() {
+                        (&mut *children.get()).as_mut_slice()[this_branch] = ChildId {
+                            index: Some(child_index)
+                        };
+                    } else {
+                        unreachable!("tree write traverser parent is garbage");
+                    }
+                },
+                ParentId::Root => {
+                    self.op.tree.root.set(Some(child_index));
+                },
+                ParentId::Detached => (),
+                ParentId::Garbage => unreachable!("garbage parent node encountered outside of GC"),
+            };
+            child_parent.set(above);
+
+            // attach this node beneath the promoted node
+            child_children.as_mut_slice()[swing_branch] = ChildId {
+                index: Some(this_index)
+            };
+            this_parent.set(ParentId::Some {
+                parent_index: child_index,
+                this_branch: swing_branch,
+            });
+            self.op.tree.mark_dirty(this_index);
+
+            // update the shape cache
+            if self.op.tree.shape_cache {
+                let swung_len = self.op.tree.cached_subtree_len(swung);
+                let this_shape = {
+                    let shapes = &mut *self.op.tree.shapes.get();
+                    let this_shape = shapes[this_index];
+                    let child_len = shapes[child_index].subtree_len;
+                    shapes[child_index] = this_shape;
+                    shapes[this_index] = Shape {
+                        subtree_len: this_shape.subtree_len - child_len + swung_len,
+                        depth: this_shape.depth + 1,
+                    };
+                    this_shape
+                };
+
+                // the swung subtree keeps its depth, but the other subtrees of both nodes move
+                for (b, child_id) in child_children.as_slice().iter().enumerate() {
+                    if let (true, Some(index)) = (b != swing_branch, child_id.index) {
+                        self.op.tree.set_subtree_depth(index, this_shape.depth + 1);
+                    }
+                }
+                for (b, child_id) in this_children.as_slice().iter().enumerate() {
+                    if let (true, Some(index)) = (b != branch, child_id.index) {
+                        self.op.tree.set_subtree_depth(index, this_shape.depth + 2);
+                    }
+                }
+            }
+
+            // follow the promoted node
+            self.index.set(child_index);
+            Ok(())
+        }
+    }
+
+    /// Perform a binary tree left rotation, promoting the child at branch 1. This is equivalent
+    /// to `rotate_child_up(1, 0)`.
+    pub fn rotate_left(&self) -> Result<(), Error> {
+        self.rotate_child_up(1, 0)
+    }
+
+    /// Perform a binary tree right rotation, promoting the child at branch 0. This is equivalent
+    /// to `rotate_child_up(0, 1)`.
+    pub fn rotate_right(&self) -> Result<(), Error> {
+        self.rotate_child_up(0, 1)
+    }
+
     unsafe fn access_node_ref(&self) -> &mut Node<T, C> {
         &mut*((&mut*self.op.tree.nodes.get())[self.index.get()].get())
     }
@@ -1368,7 +1844,7 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
     }
 
     /// If the pointed-at node has a parent, what is the branch index of this node.
-    pub fn this_branch_index(&self) -> Result<usize, NoParent> {
+    pub fn this_branch_index(&self) -> Result<usize, Error> {
         unsafe {
             if let &mut Node::Present {
                 ref parent,
@@ -1379,8 +1855,8 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> TreeWriteTraverser<'op, 't, T,
                         this_branch,
                         ..
                     } => Ok(this_branch),
-                    ParentId::Root => Err(NoParent::Root),
-                    ParentId::Detached => Err(NoParent::Detached),
+                    ParentId::Root => Err(Error::NoParent(NoParent::Root)),
+                    ParentId::Detached => Err(Error::NoParent(NoParent::Detached)),
                     ParentId::Garbage => unreachable!("garbage parent node encountered outside of GC"),
                 }
             } else {
@@ -1401,10 +1877,13 @@ impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> Deref for TreeWriteTraverser<'
 impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> DerefMut for TreeWriteTraverser<'op, 't, T, C> {
     fn deref_mut(&mut self) -> &mut T {
         unsafe {
+            self.op.tree.mark_dirty(self.index.get());
             self.access_elem_ref()
         }
     }
 }
+impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> !Send for TreeWriteTraverser<'op, 't, T, C> {}
+impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> !Sync for TreeWriteTraverser<'op, 't, T, C> {}
 impl<'op, 't: 'op, T, C: FixedSizeArray<ChildId>> IntoReadGuard<'op, T, C>
 for TreeWriteTraverser<'op, 't, T, C> {
     fn into_read_guard(self) -> NodeReadGuard<'op, T, C> {
@@ -1480,14 +1959,14 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> NodeReadGuard<'tree, T, C> {
         }
     }
 
-    pub fn child(&self, branch: usize) -> Result<Option<Self>, InvalidBranchIndex> {
+    pub fn child(&self, branch: usize) -> Result<Option<Self>, Error> {
         if let &Node::Present {
             ref children,
             ..
         } = self.node {
             unsafe {
                 (&*children.get()).as_slice().get(branch)
-                    .ok_or(InvalidBranchIndex(branch))
+                    .ok_or(Error::InvalidBranchIndex(branch))
                     .map(|child_id| child_id.index
                         .map(|child_index| Self::new(self.tree, child_index)))
             }
@@ -1501,6 +1980,30 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> NodeReadGuard<'tree, T, C> {
             index: self.index
         }
     }
+
+    /// The number of nodes in the subtree rooted at this node, including itself, if the tree
+    /// caches its shape.
+    pub fn subtree_len(&self) -> Option<usize> {
+        if self.tree.shape_cache {
+            unsafe {
+                Some((&*self.tree.shapes.get())[self.index].subtree_len)
+            }
+        } else {
+            None
+        }
+    }
+
+    /// The number of ancestors of this node within its tree or detached subtree, if the tree
+    /// caches its shape.
+    pub fn depth(&self) -> Option<usize> {
+        if self.tree.shape_cache {
+            unsafe {
+                Some((&*self.tree.shapes.get())[self.index].depth)
+            }
+        } else {
+            None
+        }
+    }
 }
 impl<'tree, T: Debug, C: FixedSizeArray<ChildId>> Debug for NodeReadGuard<'tree, T, C> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
@@ -1583,7 +2086,7 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeReadTraverser<'tree, T, C> {
         }
     }
 
-    pub fn parent(&self) -> Result<Self, NoParent> {
+    pub fn parent(&self) -> Result<Self, Error> {
         unsafe {
             if let &Node::Present {
                 ref parent,
@@ -1594,8 +2097,8 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeReadTraverser<'tree, T, C> {
                         parent_index,
                         ..
                     } => Ok(Self::new(self.inner.get().tree, parent_index)),
-                    ParentId::Root => Err(NoParent::Root),
-                    ParentId::Detached => Err(NoParent::Detached),
+                    ParentId::Root => Err(Error::NoParent(NoParent::Root)),
+                    ParentId::Detached => Err(Error::NoParent(NoParent::Detached)),
                     ParentId::Garbage => unreachable!("garbage parent node encountered outside of GC"),
                 }
             } else {
@@ -1604,12 +2107,12 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeReadTraverser<'tree, T, C> {
         }
     }
 
-    pub fn seek_parent(&self) -> Result<(), NoParent> {
+    pub fn seek_parent(&self) -> Result<(), Error> {
         self.inner.set(self.parent()?.inner.get());
         Ok(())
     }
 
-    pub fn has_child(&self, branch: usize) -> Result<bool, InvalidBranchIndex> {
+    pub fn has_child(&self, branch: usize) -> Result<bool, Error> {
         unsafe {
             if let &Node::Present {
                 ref children,
@@ -1617,7 +2120,7 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeReadTraverser<'tree, T, C> {
             } = self.access_node_ref() {
                 (&*children.get()).as_slice()
                     .get(branch)
-                    .ok_or(InvalidBranchIndex(branch))
+                    .ok_or(Error::InvalidBranchIndex(branch))
                     .map(|child_id| child_id.index.is_some())
             } else {
                 unreachable!("tree write traverser points to garbage node")
@@ -1625,7 +2128,7 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeReadTraverser<'tree, T, C> {
         }
     }
 
-    pub fn child(&self, branch: usize) -> Result<Result<Self, ChildNotFound>, InvalidBranchIndex> {
+    pub fn child(&self, branch: usize) -> Result<Self, Error> {
         unsafe {
             if let &Node::Present {
                 ref children,
@@ -1633,10 +2136,10 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeReadTraverser<'tree, T, C> {
             } = self.access_node_ref() {
                 (&*children.get()).as_slice()
                     .get(branch)
-                    .ok_or(InvalidBranchIndex(branch))
-                    .map(|child_id| match child_id.index {
+                    .ok_or(Error::InvalidBranchIndex(branch))
+                    .and_then(|child_id| match child_id.index {
                         Some(child_index) => Ok(Self::new(self.inner.get().tree, child_index)),
-                        None => Err(ChildNotFound(branch)),
+                        None => Err(Error::ChildNotFound(branch)),
                     })
             } else {
                 unreachable!("tree write traverser points to garbage node")
@@ -1644,18 +2147,12 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeReadTraverser<'tree, T, C> {
         }
     }
 
-    pub fn seek_child(&self, branch: usize) -> Result<Result<(), ChildNotFound>, InvalidBranchIndex> {
-        match self.child(branch) {
-            Ok(Ok(child)) => {
-                self.inner.set(child.inner.get());
-                Ok(Ok(()))
-            },
-            Ok(Err(e)) => Ok(Err(e)),
-            Err(e) => Err(e),
-        }
+    pub fn seek_child(&self, branch: usize) -> Result<(), Error> {
+        self.inner.set(self.child(branch)?.inner.get());
+        Ok(())
     }
 
-    pub fn this_branch_index(&self) -> Result<usize, NoParent> {
+    pub fn this_branch_index(&self) -> Result<usize, Error> {
         unsafe {
             if let &Node::Present {
                 ref parent,
@@ -1666,8 +2163,8 @@ impl<'tree, T, C: FixedSizeArray<ChildId>> TreeReadTraverser<'tree, T, C> {
                         this_branch,
                         ..
                     } => Ok(this_branch),
-                    ParentId::Root => Err(NoParent::Root),
-                    ParentId::Detached => Err(NoParent::Detached),
+                    ParentId::Root => Err(Error::NoParent(NoParent::Root)),
+                    ParentId::Detached => Err(Error::NoParent(NoParent::Detached)),
                     ParentId::Garbage => unreachable!("garbage parent node encountered outside of GC"),
                 }
             } else {
@@ -1697,8 +2194,8 @@ for &'s TreeReadTraverser<'tree, T, C> {
 }
 
 /// An opaque type which represents the index of a node in a tree. Created for the
-/// traverse_from! and traverse_read_from! macros.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+/// traverse_from! and traverse_read_from! macros, and for looking nodes up with `get`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct NodeIndex {
     index: usize,
 }
@@ -1718,6 +2215,17 @@ macro_rules! traverse_from {
     }}
 }
 
+/// Like traverse_from!, but produces none instead of panicking if the guarded node is not present
+/// and attached to the root of the tree.
+#[macro_export]
+macro_rules! try_traverse_from {
+    ( $op:expr, $node:expr  ) => {{
+        use bonzai::IntoReadGuard;
+        let index = $node.into_read_guard().index();
+        $op.traverse_from(index)
+    }}
+}
+
 /// Given a Tree and some type of guard which borrows from that tree and implements
 /// IntoReadGuard, produce a TreeReadTraverser that starts at that node.
 #[macro_export]
@@ -1729,6 +2237,17 @@ macro_rules! traverse_read_from {
     }}
 }
 
+/// Like traverse_read_from!, but produces none instead of panicking if the guarded node is not
+/// present and attached to the root of the tree.
+#[macro_export]
+macro_rules! try_traverse_read_from {
+    ( $op:expr, $node:expr  ) => {{
+        use bonzai::IntoReadGuard;
+        let index = $node.into_read_guard().index();
+        $op.traverse_read_from(index)
+    }}
+}
+
 /// Given some type of tree access which implements GetElemMut, for which mutable access is available,
 /// and some type of guard which borrows from that tree and implements IntoReadGuard, produce a
 /// mutable reference to the guarded element.
@@ -1739,4 +2258,16 @@ macro_rules! get_elem_mut {
         let index = $node.into_read_guard().index();
         $tree.get_elem_mut(index).unwrap()
     }}
+}
+
+/// Like get_elem_mut!, but produces none instead of panicking if the guarded node's index is out of
+/// range or refers to garbage, or, when accessing through a TreeOperation, if the node is not
+/// attached to the root. These are the cases in which GetElemMut::get_elem_mut gives none.
+#[macro_export]
+macro_rules! try_get_elem_mut {
+    ( $tree:expr, $node:expr ) => {{
+        use bonzai::{IntoReadGuard, GetElemMut};
+        let index = $node.into_read_guard().index();
+        $tree.get_elem_mut(index)
+    }}
 }
\ No newline at end of file
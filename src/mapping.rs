@@ -0,0 +1,98 @@
+//! Conversion of trees to other element types, preserving their layout.
+
+use {Tree, Node, ChildId, EXTENSION_SIZE, new_child_array};
+use pinned_vec::PinnedVec;
+
+use core::array::FixedSizeArray;
+use std::cell::{UnsafeCell, Cell};
+use std::mem;
+use std::vec::Vec;
+
+/// Build a tree with the same layout as the given tree, from its mapped nodes in index order.
+unsafe fn with_layout<T, B, C, E, I>(tree: &Tree<T, C>, mapped: I) -> Result<Tree<B, C>, E>
+    where C: FixedSizeArray<ChildId>, I: Iterator<Item=Result<Node<B, C>, E>> {
+
+    // build the nodes as a single chunk, rather than pushing them one at a time
+    let nodes = mapped
+        .map(|node| node.map(UnsafeCell::new))
+        .collect::<Result<Vec<_>, E>>()?;
+    Ok(Tree {
+        nodes: UnsafeCell::new(PinnedVec::from_vec(nodes, EXTENSION_SIZE)),
+        root: Cell::new(tree.root.get()),
+        garbage: UnsafeCell::new((&*tree.garbage.get()).clone()),
+        dirty: UnsafeCell::new(Vec::new()),
+        augment: None,
+        shapes: UnsafeCell::new((&*tree.shapes.get()).clone()),
+        shape_cache: tree.shape_cache,
+    })
+}
+
+impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
+    /// Convert every element of this tree, producing a tree of the same shape, with every node
+    /// at the same index. The elements are visited in an unspecified order.
+    ///
+    /// The new tree caches its shape if this tree does, but does not maintain aggregates.
+    pub fn map<B, F: FnMut(T) -> B>(self, mut f: F) -> Tree<B, C> {
+        match self.try_map(|elem| Ok::<B, ()>(f(elem))) {
+            Ok(tree) => tree,
+            Err(()) => unreachable!("infallible map failed"),
+        }
+    }
+
+    /// Convert every element of this tree, as in `map`, but stop at the first conversion which
+    /// fails, and return its error. Elements which were already converted are dropped.
+    pub fn try_map<B, E, F: FnMut(T) -> Result<B, E>>(self, mut f: F) -> Result<Tree<B, C>, E> {
+        unsafe {
+            let nodes = mem::replace(&mut *self.nodes.get(), PinnedVec::new(0)).into_vec();
+            with_layout(&self, nodes.into_iter()
+                .map(|node| Ok(match node.into_inner() {
+                    Node::Garbage {
+                        children
+                    } => Node::Garbage {
+                        children
+                    },
+                    Node::Present {
+                        elem,
+                        parent,
+                        children,
+                    } => Node::Present {
+                        elem: UnsafeCell::new(f(elem.into_inner())?),
+                        parent,
+                        children,
+                    },
+                })))
+        }
+    }
+
+    /// Convert every element of this tree by reference, as in `map`, leaving this tree intact.
+    pub fn map_ref<B, F: FnMut(&T) -> B>(&self, mut f: F) -> Tree<B, C> {
+        unsafe {
+            let copy_children = |children: &C| {
+                let mut copy: C = new_child_array();
+                copy.as_mut_slice().copy_from_slice(children.as_slice());
+                copy
+            };
+            let mapped = with_layout(self, (&*self.nodes.get()).iter()
+                .map(|node| Ok::<_, ()>(match &*node.get() {
+                    &Node::Garbage {
+                        ref children
+                    } => Node::Garbage {
+                        children: copy_children(children),
+                    },
+                    &Node::Present {
+                        ref elem,
+                        ref parent,
+                        ref children,
+                    } => Node::Present {
+                        elem: UnsafeCell::new(f(&*elem.get())),
+                        parent: Cell::new(parent.get()),
+                        children: UnsafeCell::new(copy_children(&*children.get())),
+                    },
+                })));
+            match mapped {
+                Ok(tree) => tree,
+                Err(()) => unreachable!("infallible map failed"),
+            }
+        }
+    }
+}
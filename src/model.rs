@@ -0,0 +1,297 @@
+//! A boxed reference model of guard operations, shared by the randomized tests and the fuzz
+//! target. Only compiled for tests, or with the `fuzzing` feature.
+
+use {Tree, ChildId, NodeReadGuard, NodeOwnedGuard, IntoReadGuard, IntoWriteGuard};
+
+use std::rc::Rc;
+use std::cell::Cell;
+use std::mem;
+use std::vec::Vec;
+
+/// A node within a detached subtree: the subtree is selected modulo the number of detached
+/// subtrees, and the path of branches is followed from its root until it reaches a missing child.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub subtree: usize,
+    pub path: Vec<usize>,
+}
+
+/// One step of a sequence of operations. Indices are taken modulo the number of choices, and steps
+/// which do not apply to the current state are skipped, so that every sequence is valid.
+#[derive(Debug, Clone)]
+pub enum Op {
+    /// Put a new element at a branch of the target node.
+    PutChildElem(Target, usize),
+    /// Take the child at a branch of the target node, as a new detached subtree.
+    TakeChild(Target, usize),
+    /// Put another detached subtree at a branch of the target node.
+    PutChildTree(Target, usize, usize),
+    /// Detach the target node, unless it is the root of its subtree.
+    Detach(Target),
+    /// Drop a detached subtree.
+    Drop(usize),
+    /// Turn a detached subtree into its root element.
+    IntoElem(usize),
+    /// Take the tree's root, as a new detached subtree.
+    TakeRoot,
+    /// Try to put a detached subtree as the tree's root.
+    PutRootTree(usize),
+    /// Create a new detached node.
+    NewDetached,
+    /// Drop the operation, collecting its garbage, after putting a detached subtree as the root if
+    /// there is none.
+    Collect(usize),
+}
+
+impl Op {
+    /// Decode a sequence of operations from arbitrary bytes, for fuzzing.
+    pub fn decode(bytes: &[u8]) -> Vec<Op> {
+        fn target(next: &mut dyn FnMut() -> usize) -> Target {
+            let subtree = next();
+            let path = (0..next() % 4).map(|_| next()).collect();
+            Target {
+                subtree,
+                path,
+            }
+        }
+
+        let mut bytes = bytes.iter().map(|&byte| byte as usize);
+        let mut ops = Vec::new();
+        while let Some(tag) = bytes.next() {
+            let mut next = || bytes.next().unwrap_or(0);
+            ops.push(match tag % 10 {
+                0 => Op::PutChildElem(target(&mut next), next()),
+                1 => Op::TakeChild(target(&mut next), next()),
+                2 => Op::PutChildTree(target(&mut next), next(), next()),
+                3 => Op::Detach(target(&mut next)),
+                4 => Op::Drop(next()),
+                5 => Op::IntoElem(next()),
+                6 => Op::TakeRoot,
+                7 => Op::PutRootTree(next()),
+                8 => Op::NewDetached,
+                _ => Op::Collect(next()),
+            });
+        }
+        ops
+    }
+}
+
+/// An element which counts how many elements are alive, to detect leaks and double drops.
+struct Tracked {
+    value: u32,
+    live: Rc<Cell<usize>>,
+}
+impl Tracked {
+    fn new(value: u32, live: &Rc<Cell<usize>>) -> Self {
+        live.set(live.get() + 1);
+        Tracked {
+            value,
+            live: live.clone(),
+        }
+    }
+}
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.live.set(self.live.get() - 1);
+    }
+}
+
+/// A reference model of a ternary tree, as plain boxes.
+struct Model {
+    value: u32,
+    children: [Option<Box<Model>>; 3],
+}
+impl Model {
+    fn leaf(value: u32) -> Self {
+        Model {
+            value,
+            children: [None, None, None],
+        }
+    }
+
+    /// Follow a path of branches while the children exist, returning the branches taken.
+    fn resolve(&self, path: &[usize]) -> Vec<usize> {
+        let mut resolved = Vec::new();
+        let mut curr = self;
+        for &branch in path {
+            match curr.children[branch % 3].as_ref() {
+                Some(child) => {
+                    resolved.push(branch % 3);
+                    curr = child;
+                },
+                None => break,
+            };
+        }
+        resolved
+    }
+
+    fn at(&mut self, path: &[usize]) -> &mut Model {
+        path.iter().fold(self, |node, &branch| node.children[branch].as_mut().unwrap())
+    }
+
+    fn len(&self) -> usize {
+        1 + self.children.iter().flat_map(|child| child.iter()).map(|child| child.len()).sum::<usize>()
+    }
+
+    fn matches(&self, node: NodeReadGuard<Tracked, [ChildId; 3]>) -> bool {
+        node.value == self.value && (0..3).all(|branch| match (self.children[branch].as_ref(), node.child(branch).unwrap()) {
+            (Some(model), Some(child)) => model.matches(child),
+            (None, None) => true,
+            _ => false,
+        })
+    }
+}
+
+/// Apply a sequence of operations to a tree and to the reference model, checking after every step
+/// that they agree and that the tree validates, and after every operation that garbage collection
+/// leaves exactly the live elements. Panics if they ever disagree.
+pub fn check(ops: &[Op], shape_cache: bool) {
+    let live = Rc::new(Cell::new(0));
+    let mut tree = Tree::<Tracked, [ChildId; 3]>::new();
+    if shape_cache {
+        tree.enable_shape_cache();
+    }
+    let mut model_root: Option<Model> = None;
+    let mut next_value = 0;
+    let mut ops = ops.iter();
+
+    loop {
+        let mut exhausted = true;
+        {
+            let op = tree.operation();
+            let mut pool: Vec<NodeOwnedGuard<Tracked, [ChildId; 3]>> = Vec::new();
+            let mut model_pool: Vec<Model> = Vec::new();
+            let mut keep = 0;
+
+            for step in &mut ops {
+                next_value += 1;
+                let value = next_value;
+                match step {
+                    &Op::PutChildElem(ref target, branch) if !pool.is_empty() => {
+                        let i = target.subtree % pool.len();
+                        let path = model_pool[i].resolve(&target.path);
+                        let mut node = (&mut pool[i]).into_write_guard();
+                        for &b in &path {
+                            node = node.into_split().1.into_child_write(b).unwrap().unwrap();
+                        }
+                        node.children().put_child_elem(branch % 3, Tracked::new(value, &live)).unwrap();
+                        model_pool[i].at(&path).children[branch % 3] = Some(Box::new(Model::leaf(value)));
+                    },
+                    &Op::TakeChild(ref target, branch) if !pool.is_empty() => {
+                        let i = target.subtree % pool.len();
+                        let path = model_pool[i].resolve(&target.path);
+                        let taken = {
+                            let mut node = (&mut pool[i]).into_write_guard();
+                            for &b in &path {
+                                node = node.into_split().1.into_child_write(b).unwrap().unwrap();
+                            }
+                            node.children().take_child(branch % 3).unwrap()
+                        };
+                        if let Some(taken) = taken {
+                            pool.push(taken);
+                            let model_taken = model_pool[i].at(&path).children[branch % 3].take().unwrap();
+                            model_pool.push(*model_taken);
+                        }
+                    },
+                    &Op::PutChildTree(ref target, branch, other) if pool.len() >= 2 => {
+                        let i = target.subtree % pool.len();
+                        let j = (i + 1 + other % (pool.len() - 1)) % pool.len();
+                        let subtree = pool.remove(j);
+                        let model_subtree = model_pool.remove(j);
+                        let i = if j < i { i - 1 } else { i };
+                        let path = model_pool[i].resolve(&target.path);
+                        let mut node = (&mut pool[i]).into_write_guard();
+                        for &b in &path {
+                            node = node.into_split().1.into_child_write(b).unwrap().unwrap();
+                        }
+                        node.children().put_child_tree(branch % 3, subtree).unwrap();
+                        model_pool[i].at(&path).children[branch % 3] = Some(Box::new(model_subtree));
+                    },
+                    &Op::Detach(ref target) if !pool.is_empty() => {
+                        let i = target.subtree % pool.len();
+                        let mut path = model_pool[i].resolve(&target.path);
+                        if !path.is_empty() {
+                            let detached = {
+                                let mut node = (&mut pool[i]).into_write_guard();
+                                for &b in &path {
+                                    node = node.into_split().1.into_child_write(b).unwrap().unwrap();
+                                }
+                                node.detach()
+                            };
+                            pool.push(detached);
+                            let last = path.pop().unwrap();
+                            let model_detached = model_pool[i].at(&path).children[last].take().unwrap();
+                            model_pool.push(*model_detached);
+                        }
+                    },
+                    &Op::Drop(i) if !pool.is_empty() => {
+                        let i = i % pool.len();
+                        pool.remove(i);
+                        model_pool.remove(i);
+                    },
+                    &Op::IntoElem(i) if !pool.is_empty() => {
+                        let i = i % pool.len();
+                        let elem = pool.remove(i).into_elem();
+                        assert_eq!(elem.value, model_pool.remove(i).value);
+                    },
+                    &Op::TakeRoot => if let Some(root) = op.take_root() {
+                        pool.push(root);
+                        model_pool.push(model_root.take().unwrap());
+                    },
+                    &Op::PutRootTree(i) if !pool.is_empty() => {
+                        let i = i % pool.len();
+                        match op.try_put_root_tree(pool.remove(i)) {
+                            Ok(()) => model_root = Some(model_pool.remove(i)),
+                            Err(err) => pool.insert(i, err.attempted_to_put),
+                        };
+                    },
+                    &Op::NewDetached => {
+                        pool.push(op.new_detached(Tracked::new(value, &live)));
+                        model_pool.push(Model::leaf(value));
+                    },
+                    &Op::Collect(i) => {
+                        keep = i;
+                        exhausted = false;
+                        break;
+                    },
+                    _ => (),
+                };
+
+                assert_eq!(op.validate(), Ok(()));
+                match (model_root.as_ref(), op.read_root()) {
+                    (Some(model), Some(root)) => assert!(model.matches(root)),
+                    (None, None) => (),
+                    _ => panic!("root presence differs from model"),
+                };
+                for (subtree, model) in pool.iter().zip(&model_pool) {
+                    assert!(model.matches(subtree.into_read_guard()));
+                }
+            }
+
+            // keep a subtree as the root, so that the tree grows across operations
+            if model_root.is_none() && !pool.is_empty() {
+                let i = keep % pool.len();
+                op.try_put_root_tree(pool.remove(i)).unwrap();
+                model_root = Some(model_pool.remove(i));
+            }
+        }
+
+        // dropping the operation collects the garbage
+        assert_eq!(tree.validate(), Ok(()));
+        let len = model_root.as_ref().map(Model::len).unwrap_or(0);
+        assert_eq!(live.get(), len);
+        assert_eq!(unsafe { (&*tree.nodes.get()).len() }, len);
+        match (model_root.as_ref(), tree.read_root()) {
+            (Some(model), Some(root)) => assert!(model.matches(root)),
+            (None, None) => (),
+            _ => panic!("root presence differs from model"),
+        };
+
+        if exhausted {
+            break;
+        }
+    }
+
+    mem::drop(tree);
+    assert_eq!(live.get(), 0);
+}
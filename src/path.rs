@@ -0,0 +1,255 @@
+//! Addressing of nodes by the sequence of branches leading to them.
+
+use {Tree, Node, ParentId, ChildId, NodeIndex, NodeReadGuard, NodeWriteGuard, NodeAddress};
+
+use core::array::FixedSizeArray;
+use std::ops::Deref;
+use std::iter::FromIterator;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::marker::PhantomData;
+use std::fmt::{Display, Formatter};
+use std::fmt;
+use std::vec::Vec;
+
+/// The sequence of branch indices leading from the root of a tree to a node. Unlike a
+/// `NodeIndex`, a path is independent of how the nodes are stored, so it remains meaningful
+/// across garbage collection, and across trees.
+///
+/// Paths are displayed and parsed as branch indices separated by slashes, such as `0/2/1`. The
+/// path to the root is empty, and is displayed as the empty string.
+#[derive(Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct BranchPath {
+    branches: Vec<usize>,
+}
+impl BranchPath {
+    /// The path to the root.
+    pub fn root() -> Self {
+        BranchPath::default()
+    }
+
+    /// Extend the path by one branch.
+    pub fn push(&mut self, branch: usize) {
+        self.branches.push(branch);
+    }
+
+    /// Remove the last branch from the path, returning it, unless this is the path to the root.
+    pub fn pop(&mut self) -> Option<usize> {
+        self.branches.pop()
+    }
+
+    /// The path to this path's child at a certain branch.
+    pub fn child(&self, branch: usize) -> Self {
+        let mut child = self.clone();
+        child.push(branch);
+        child
+    }
+
+    /// The path to this path's parent, unless this is the path to the root.
+    pub fn parent(&self) -> Option<Self> {
+        self.branches.split_last()
+            .map(|(_, parent)| BranchPath::from(parent))
+    }
+}
+impl Deref for BranchPath {
+    type Target = [usize];
+
+    fn deref(&self) -> &[usize] {
+        &self.branches
+    }
+}
+impl From<Vec<usize>> for BranchPath {
+    fn from(branches: Vec<usize>) -> Self {
+        BranchPath {
+            branches
+        }
+    }
+}
+impl<'a> From<&'a [usize]> for BranchPath {
+    fn from(branches: &'a [usize]) -> Self {
+        BranchPath::from(branches.to_vec())
+    }
+}
+impl FromIterator<usize> for BranchPath {
+    fn from_iter<I: IntoIterator<Item=usize>>(iter: I) -> Self {
+        BranchPath::from(iter.into_iter().collect::<Vec<usize>>())
+    }
+}
+impl Display for BranchPath {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        for (i, branch) in self.branches.iter().enumerate() {
+            if i > 0 {
+                f.write_str("/")?;
+            }
+            write!(f, "{}", branch)?;
+        }
+        Ok(())
+    }
+}
+impl FromStr for BranchPath {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, ParseIntError> {
+        if s.is_empty() {
+            Ok(BranchPath::root())
+        } else {
+            s.split('/')
+                .map(|branch| branch.parse())
+                .collect()
+        }
+    }
+}
+
+/// Error type for requesting write guards to subtrees which overlap, because one of the paths
+/// is a prefix of another. Contains the positions of the two paths.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Overlap(pub usize, pub usize);
+
+/// Arrays of paths, for which an array of the same length, of any type, can be built. This is
+/// implemented for `[BranchPath; N]` for every `N` up to 16.
+pub trait PathArray<X> {
+    /// An array of `X` with one entry for each path.
+    type Array;
+
+    /// The paths, as a slice.
+    fn paths(&self) -> &[BranchPath];
+
+    /// Build the array by calling a function with each position, in order.
+    fn from_fn<F: FnMut(usize) -> X>(f: F) -> Self::Array;
+}
+
+macro_rules! path_arrays {
+    ($len:expr; $($position:expr),*) => {
+        impl<X> PathArray<X> for [BranchPath; $len] {
+            type Array = [X; $len];
+
+            fn paths(&self) -> &[BranchPath] {
+                self
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn from_fn<F: FnMut(usize) -> X>(mut f: F) -> [X; $len] {
+                [$(f($position)),*]
+            }
+        }
+    };
+}
+
+path_arrays!(0;);
+path_arrays!(1; 0);
+path_arrays!(2; 0, 1);
+path_arrays!(3; 0, 1, 2);
+path_arrays!(4; 0, 1, 2, 3);
+path_arrays!(5; 0, 1, 2, 3, 4);
+path_arrays!(6; 0, 1, 2, 3, 4, 5);
+path_arrays!(7; 0, 1, 2, 3, 4, 5, 6);
+path_arrays!(8; 0, 1, 2, 3, 4, 5, 6, 7);
+path_arrays!(9; 0, 1, 2, 3, 4, 5, 6, 7, 8);
+path_arrays!(10; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9);
+path_arrays!(11; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10);
+path_arrays!(12; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11);
+path_arrays!(13; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12);
+path_arrays!(14; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13);
+path_arrays!(15; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14);
+path_arrays!(16; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+
+/// Follow a path from a node to a descendant's index, if every branch of the path exists.
+unsafe fn index_from<T, C: FixedSizeArray<ChildId>>(tree: &Tree<T, C>, index: usize, path: &[usize]) -> Option<usize> {
+    let nodes = &*tree.nodes.get();
+    let mut curr = index;
+    for &branch in path {
+        curr = match &*nodes[curr].get() {
+            &Node::Present {
+                ref children,
+                ..
+            } => (&*children.get()).as_slice().get(branch)?.index?,
+            &Node::Garbage { .. } => unreachable!("path leads through garbage"),
+        };
+    }
+    Some(curr)
+}
+
+/// Follow a path from the root to a node index, if every branch of the path exists.
+unsafe fn index_at<T, C: FixedSizeArray<ChildId>>(tree: &Tree<T, C>, path: &[usize]) -> Option<usize> {
+    index_from(tree, tree.root.get()?, path)
+}
+
+impl<'a> NodeAddress for &'a BranchPath {
+    /// A path resolves if every branch of it exists. This is an O(L) operation, where L is the
+    /// length of the path.
+    fn resolve<T, C: FixedSizeArray<ChildId>>(self, tree: &Tree<T, C>) -> Option<NodeIndex> {
+        unsafe {
+            index_at(tree, self)
+                .map(|index| NodeIndex {
+                    index
+                })
+        }
+    }
+}
+
+impl<'tree, T, C: FixedSizeArray<ChildId>> NodeReadGuard<'tree, T, C> {
+    /// The path to this node from the root. For a node in a detached subtree, this is the path
+    /// from the root of that subtree.
+    pub fn path(&self) -> BranchPath {
+        unsafe {
+            let nodes = &*self.tree.nodes.get();
+            let mut branches = Vec::new();
+            let mut curr = self.index;
+            loop {
+                match &*nodes[curr].get() {
+                    &Node::Present {
+                        ref parent,
+                        ..
+                    } => match parent.get() {
+                        ParentId::Some {
+                            parent_index,
+                            this_branch,
+                        } => {
+                            branches.push(this_branch);
+                            curr = parent_index;
+                        },
+                        ParentId::Root | ParentId::Detached => break,
+                        ParentId::Garbage => unreachable!("encountered garbage parent outside of GC"),
+                    },
+                    &Node::Garbage { .. } => unreachable!("path of garbage node"),
+                };
+            }
+            branches.reverse();
+            BranchPath::from(branches)
+        }
+    }
+}
+
+impl<'op, 'node, 't: 'op, T, C: FixedSizeArray<ChildId>> NodeWriteGuard<'op, 'node, 't, T, C> {
+    /// Borrow write guards to several descendants at once, given their paths relative to this
+    /// node. Fails if any path is a prefix of another, including if two paths are equal, since
+    /// their subtrees would overlap. Produces none if any path does not lead to a node.
+    pub fn get_many_mut<'s, P>(&'s mut self, paths: &P) -> Result<Option<P::Array>, Overlap>
+        where P: PathArray<NodeWriteGuard<'op, 's, 't, T, C>> {
+
+        let paths = paths.paths();
+        for (i, a) in paths.iter().enumerate() {
+            for (j, b) in paths.iter().enumerate().skip(i + 1) {
+                if a.starts_with(b) || b.starts_with(a) {
+                    return Err(Overlap(i, j));
+                }
+            }
+        }
+
+        let mut indices = Vec::with_capacity(paths.len());
+        for path in paths {
+            match unsafe { index_from(self.op.tree, self.index, path) } {
+                Some(index) => indices.push(index),
+                None => return Ok(None),
+            };
+        }
+
+        let op = self.op;
+        Ok(Some(P::from_fn(|position| NodeWriteGuard {
+            op,
+            index: indices[position],
+
+            p1: PhantomData,
+        })))
+    }
+}
@@ -5,6 +5,8 @@ use std::fmt;
 use std::iter::{Iterator, IntoIterator};
 use std::slice::Iter;
 use std::mem;
+use std::vec::Vec;
+use std::boxed::Box;
 
 pub struct PinnedVec<T> {
     vec: Vec<T>,
@@ -39,6 +41,14 @@ impl<T> PinnedVec<T> {
         }
     }
 
+    pub fn from_vec(vec: Vec<T>, extension_size: usize) -> Self {
+        PinnedVec {
+            vec,
+            next: None,
+            extension_size
+        }
+    }
+
     pub fn push(&mut self, elem: T) {
         if self.vec.len() < self.vec.capacity() {
             self.vec.push(elem);
@@ -93,6 +103,21 @@ impl<T> PinnedVec<T> {
         }
     }
 
+    pub fn into_vec(mut self) -> Vec<T> {
+        self.defragment();
+        self.vec
+    }
+
+    pub fn chunk_lens(&self) -> Vec<usize> {
+        let mut lens = Vec::new();
+        let mut option_curr = Some(self);
+        while let Some(curr) = option_curr {
+            lens.push(curr.vec.len());
+            option_curr = curr.next.as_ref().map(|next| &**next);
+        }
+        lens
+    }
+
     pub fn iter<'a>(&'a self) -> PinnedVecIter<'a, T> {
         PinnedVecIter {
             curr: Some((self, self.vec.iter()))
@@ -110,7 +135,7 @@ impl<T> Index<usize> for PinnedVec<T> {
             debug_assert_eq!(self.vec.len(), self.vec.capacity(),
                              "PinnedVec index out of bounds {}", index);
             self.next.as_ref()
-                .ok_or_else(|| format!("PinnedVec index out of bounds {}", index)).unwrap()
+                .expect("PinnedVec index out of bounds")
                 .index(index - self.vec.len())
         }
     }
@@ -123,7 +148,7 @@ impl<T> IndexMut<usize> for PinnedVec<T> {
             debug_assert_eq!(self.vec.len(), self.vec.capacity(),
                              "PinnedVec index out of bounds {}", index);
             self.next.as_mut()
-                .ok_or_else(|| format!("PinnedVec index out of bounds {}", index)).unwrap()
+                .expect("PinnedVec index out of bounds")
                 .index_mut(index - self.vec.len())
         }
     }
@@ -0,0 +1,87 @@
+//! Removal of every subtree which fails a predicate.
+
+use {TreeOperation, Node, ParentId, ChildId, subtree_pre_order};
+
+use core::array::FixedSizeArray;
+use std::vec::Vec;
+
+impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
+    /// Remove every node for which the predicate returns false, along with all its descendants.
+    /// The predicate is given each element, and the sequence of branch indices leading to it from
+    /// the root. It is called in pre-order, and is not called for descendants of removed nodes.
+    ///
+    /// The removed nodes become garbage, to be collected in a single sweep when this operation
+    /// is dropped.
+    pub fn retain<F: FnMut(&T, &[usize]) -> bool>(&mut self, mut f: F) {
+        unsafe {
+            let tree = &*self.tree;
+            let nodes = &*tree.nodes.get();
+
+            let root_index = match tree.root.get() {
+                Some(root_index) => root_index,
+                None => return,
+            };
+
+            let mut path: Vec<usize> = Vec::new();
+            let mut walk = subtree_pre_order(tree, root_index);
+            while let Some((index, depth)) = walk.next() {
+                let (keep, parent) = if let &Node::Present {
+                    ref elem,
+                    ref parent,
+                    ..
+                } = &*nodes[index].get() {
+                    path.truncate(depth.saturating_sub(1));
+                    if let ParentId::Some {
+                        this_branch,
+                        ..
+                    } = parent.get() {
+                        path.push(this_branch);
+                    }
+
+                    (f(&*elem.get(), &path), parent.get())
+                } else {
+                    unreachable!("retain visited garbage node")
+                };
+                if keep {
+                    continue;
+                }
+                walk.skip_children();
+
+                // detach the node
+                match parent {
+                    ParentId::Some {
+                        parent_index,
+                        this_branch,
+                    } => {
+                        let removed_len = tree.cached_subtree_len(ChildId {
+                            index: Some(index)
+                        });
+                        if let &Node::Present {
+                            ref children,
+                            ..
+                        } = &*nodes[parent_index].get() {
+                            (&mut *children.get()).as_mut_slice()[this_branch] = ChildId {
+                                index: None
+                            };
+                        } else {
+                            unreachable!("retain parent index points to garbage");
+                        }
+                        tree.mark_dirty(parent_index);
+                        tree.adjust_subtree_lens(parent_index, -(removed_len as isize));
+                    },
+                    ParentId::Root => tree.root.set(None),
+                    ParentId::Detached | ParentId::Garbage => unreachable!("retain visited unreachable node"),
+                };
+
+                // mark it as garbage, which also marks its descendants when collected
+                (&mut *(&nodes[index]).get()).take_elem_become_garbage();
+                (&mut *tree.garbage.get()).push(index);
+            }
+        }
+    }
+
+    /// Remove every node deeper than the given depth, where the root has a depth of 0.
+    pub fn prune_depth(&mut self, max_depth: usize) {
+        self.retain(|_, path| path.len() <= max_depth);
+    }
+}
@@ -0,0 +1,91 @@
+//! Searching for nodes by predicate, or by following a guide from the root.
+
+use {Tree, ChildId, NodeReadGuard, Error, PreOrder};
+
+use core::array::FixedSizeArray;
+use std::vec::Vec;
+
+impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
+    /// Find the first node, in pre-order, whose element satisfies the predicate.
+    pub fn find<'tree, F: FnMut(&T) -> bool>(&'tree self, predicate: F) -> Option<NodeReadGuard<'tree, T, C>> {
+        self.find_all(predicate).next()
+    }
+
+    /// Iterate over every node whose element satisfies the predicate, in pre-order.
+    pub fn find_all<'tree, F: FnMut(&T) -> bool>(&'tree self, predicate: F) -> FindAll<'tree, T, C, F> {
+        FindAll {
+            walk: PreOrder::new(self.read_root(), read_children),
+            predicate,
+        }
+    }
+
+    /// Descend from the root, as in `NodeReadGuard::descend`, or none if the tree is empty.
+    pub fn descend<'tree, F: FnMut(&T) -> Option<usize>>(&'tree self, guide: F)
+        -> Option<(NodeReadGuard<'tree, T, C>, Option<usize>)> {
+        self.read_root().map(|root| root.descend(guide))
+    }
+
+    /// Descend from the root, as in `NodeReadGuard::try_descend`, or none if the tree is empty.
+    pub fn try_descend<'tree, F: FnMut(&T) -> Option<usize>>(&'tree self, guide: F)
+        -> Result<Option<(NodeReadGuard<'tree, T, C>, Option<usize>)>, Error> {
+        match self.read_root() {
+            Some(root) => root.try_descend(guide).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'tree, T, C: FixedSizeArray<ChildId>> NodeReadGuard<'tree, T, C> {
+    /// Descend from this node, following the branch which the guide returns for each element,
+    /// until the guide returns none, or the branch it returns has no child. This is the core loop
+    /// of a search tree lookup.
+    ///
+    /// Returns the last node visited, and the branch which it lacked a child at, if the descent
+    /// ended there. In a search tree, that is where a missing element would be inserted.
+    ///
+    /// Panics if the guide returns a branch index which is invalid for the branch factor.
+    pub fn descend<F: FnMut(&T) -> Option<usize>>(self, guide: F) -> (Self, Option<usize>) {
+        self.try_descend(guide).unwrap()
+    }
+
+    /// Descend from this node, as in `descend`, but fail instead of panicking if the guide
+    /// returns a branch index which is invalid for the branch factor.
+    pub fn try_descend<F: FnMut(&T) -> Option<usize>>(self, mut guide: F) -> Result<(Self, Option<usize>), Error> {
+        let mut node = self;
+        loop {
+            let branch = match guide(node.elem) {
+                Some(branch) => branch,
+                None => return Ok((node, None)),
+            };
+            match node.child(branch)? {
+                Some(child) => node = child,
+                None => return Ok((node, Some(branch))),
+            };
+        }
+    }
+}
+
+/// Push the children of a node, in branch order.
+fn read_children<'tree, T, C>(node: &NodeReadGuard<'tree, T, C>, children: &mut Vec<NodeReadGuard<'tree, T, C>>)
+    where C: FixedSizeArray<ChildId> {
+    let mut branch = 0;
+    while let Ok(child) = node.child(branch) {
+        children.extend(child);
+        branch += 1;
+    }
+}
+
+/// An iterator over the nodes which satisfy a predicate, created by `Tree::find_all`.
+pub struct FindAll<'tree, T: 'tree, C: FixedSizeArray<ChildId> + 'tree, F> {
+    walk: PreOrder<NodeReadGuard<'tree, T, C>,
+                   fn(&NodeReadGuard<'tree, T, C>, &mut Vec<NodeReadGuard<'tree, T, C>>)>,
+    predicate: F,
+}
+impl<'tree, T, C: FixedSizeArray<ChildId>, F: FnMut(&T) -> bool> Iterator for FindAll<'tree, T, C, F> {
+    type Item = NodeReadGuard<'tree, T, C>;
+
+    fn next(&mut self) -> Option<NodeReadGuard<'tree, T, C>> {
+        let predicate = &mut self.predicate;
+        self.walk.by_ref().find(|node| predicate(node.elem))
+    }
+}
@@ -1,10 +1,12 @@
 
 use super::*;
+use proptest::prelude::*;
 
 #[test]
 fn bad_test() {
     // the test here, is that this code shouldn't compile
     // if this code compiles, then bonzai is broken
+    // tests/compile-fail checks this, and similar programs, with trybuild
 
     // example compile error:
     /*
@@ -45,4 +47,1166 @@ error[E0499]: cannot borrow `op` as mutable more than once at a time
     println!("{}", ae);
     println!("{}", be);
     */
-}
\ No newline at end of file
+}
+
+#[test]
+fn rotate_left_and_right() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(2);
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, 1).unwrap();
+        children.put_child_elem(1, 4).unwrap();
+        let mut right = children.borrow_child_write(1).unwrap().unwrap();
+        let mut right_children = right.children();
+        right_children.put_child_elem(0, 3).unwrap();
+        right_children.put_child_elem(1, 5).unwrap();
+    }
+    {
+        let mut op = tree.operation();
+        let trav = op.traverse_root().unwrap();
+        trav.rotate_left().unwrap();
+        assert_eq!(*trav, 4);
+        assert_eq!(trav.above_me(), AboveMe::Root);
+        trav.seek_child(0).unwrap();
+        assert_eq!(*trav, 2);
+        assert_eq!(trav.this_branch_index().unwrap(), 0);
+        trav.seek_child(1).unwrap();
+        assert_eq!(*trav, 3);
+        trav.seek_parent().unwrap();
+        trav.seek_parent().unwrap();
+        assert_eq!(*trav, 4);
+    }
+    {
+        let root = tree.read_root().unwrap();
+        assert_eq!(*root, 4);
+        assert_eq!(*root.child(1).unwrap().unwrap(), 5);
+        let left = root.child(0).unwrap().unwrap();
+        assert_eq!(*left, 2);
+        assert_eq!(*left.child(0).unwrap().unwrap(), 1);
+        assert_eq!(*left.child(1).unwrap().unwrap(), 3);
+    }
+    {
+        let mut op = tree.operation();
+        let trav = op.traverse_root().unwrap();
+        trav.rotate_right().unwrap();
+        assert_eq!(*trav, 2);
+        assert_eq!(trav.rotate_child_up(0, 2), Err(Error::InvalidBranchIndex(2)));
+        trav.seek_child(0).unwrap();
+        assert_eq!(trav.rotate_right(), Err(Error::ChildNotFound(0)));
+    }
+    let root = tree.read_root().unwrap();
+    assert_eq!(*root, 2);
+    assert_eq!(*root.child(0).unwrap().unwrap(), 1);
+    let right = root.child(1).unwrap().unwrap();
+    assert_eq!(*right, 4);
+    assert_eq!(*right.child(0).unwrap().unwrap(), 3);
+    assert_eq!(*right.child(1).unwrap().unwrap(), 5);
+}
+
+#[test]
+fn bonzai_map_matches_btree_map() {
+    use collections::{BonzaiMap, BonzaiSet};
+    use std::collections::BTreeMap;
+
+    let mut map = BonzaiMap::new();
+    let mut model = BTreeMap::new();
+    let mut state: u64 = 0x2545F4914F6CDD1D;
+    for i in 0..4000 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let key = state % 500;
+        match state % 3 {
+            0 => assert_eq!(map.remove(&key), model.remove(&key)),
+            _ => assert_eq!(map.insert(key, i), model.insert(key, i)),
+        };
+        assert_eq!(map.len(), model.len());
+    }
+    assert!(map.iter().eq(model.iter()));
+    assert!(map.range(100..200).eq(model.range(100..200)));
+    assert!(map.range(..=250).eq(model.range(..=250)));
+    assert_eq!(map.get(&42), model.get(&42));
+
+    *map.entry(1000).or_insert(0) += 5;
+    *map.entry(1000).or_insert(0) += 5;
+    assert_eq!(map.get(&1000), Some(&10));
+    if let collections::map::Entry::Occupied(entry) = map.entry(1000) {
+        assert_eq!(entry.remove(), 10);
+    }
+    assert!(!map.contains_key(&1000));
+
+    let set: BonzaiSet<u64> = model.keys().cloned().collect();
+    assert!(set.iter().eq(model.keys()));
+}
+
+#[test]
+fn collect_descendants_of_collected_nodes() {
+    use std::rc::Rc;
+
+    let token = Rc::new(());
+    let mut tree = Tree::<Rc<()>, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(token.clone());
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, token.clone()).unwrap();
+        let mut child = children.borrow_child_write(0).unwrap().unwrap();
+        let mut grandchildren = child.children();
+        grandchildren.put_child_elem(0, token.clone()).unwrap();
+        let mut grandchild = grandchildren.borrow_child_write(0).unwrap().unwrap();
+        grandchild.children().put_child_elem(0, token.clone()).unwrap();
+    }
+    {
+        let mut op = tree.operation();
+        op.write_root().unwrap().children().take_child(0).unwrap().unwrap();
+    }
+    assert_eq!(unsafe { (&*tree.nodes.get()).len() }, 1);
+    assert_eq!(Rc::strong_count(&token), 2);
+}
+
+#[test]
+fn collect_relocated_garbage() {
+    use std::rc::Rc;
+
+    let token = Rc::new(());
+    let mut tree = Tree::<Rc<()>, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(token.clone());
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, token.clone()).unwrap();
+        children.borrow_child_write(0).unwrap().unwrap().children().put_child_elem(0, token.clone()).unwrap();
+        children.put_child_elem(1, token.clone()).unwrap();
+    }
+    {
+        // the grandchild is marked as garbage when its parent is collected, and is then moved
+        // into the hole left by its uncle, before its own index comes up
+        let mut op = tree.operation();
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.take_child(1).unwrap().unwrap();
+        children.take_child(0).unwrap().unwrap();
+    }
+    assert_eq!(unsafe { (&*tree.nodes.get()).len() }, 1);
+    assert_eq!(Rc::strong_count(&token), 2);
+}
+
+#[test]
+fn collect_below_uncollected_garbage() {
+    use std::rc::Rc;
+
+    let token = Rc::new(());
+    let mut tree = Tree::<Rc<()>, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(token.clone());
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, token.clone()).unwrap();
+        children.put_child_elem(1, token.clone()).unwrap();
+        children.borrow_child_write(0).unwrap().unwrap().children().put_child_elem(0, token.clone()).unwrap();
+    }
+    {
+        // the grandchild is moved while its parent is garbage which is still waiting to be
+        // collected, so the garbage parent's child index must follow it
+        let mut op = tree.operation();
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.take_child(0).unwrap().unwrap();
+        children.take_child(1).unwrap().unwrap();
+    }
+    assert_eq!(unsafe { (&*tree.nodes.get()).len() }, 1);
+    assert_eq!(Rc::strong_count(&token), 2);
+}
+
+struct Weight(i32);
+
+impl Augment for Weight {
+    type Agg = i32;
+
+    fn combine(&self, children: &[Option<&i32>]) -> i32 {
+        children.iter().filter_map(|&child| child).fold(self.0, |sum, &child| sum + child)
+    }
+
+    fn leaf(&self) -> i32 {
+        self.0
+    }
+}
+
+/// The leftmost element of each subtree, which looks at its children by branch.
+struct Leftmost(char);
+
+impl Augment for Leftmost {
+    type Agg = char;
+
+    fn combine(&self, children: &[Option<&char>]) -> char {
+        children[0].cloned().unwrap_or(self.0)
+    }
+
+    fn leaf(&self) -> char {
+        self.0
+    }
+}
+
+#[test]
+fn augmented_subtree_sums() {
+    let mut tree = Tree::<Augmented<Weight>, [ChildId; 2]>::new_augmented();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(Augmented::new(Weight(1)));
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, Augmented::new(Weight(2))).unwrap();
+        children.put_child_elem(1, Augmented::new(Weight(3))).unwrap();
+        let mut left = children.borrow_child_write(0).unwrap().unwrap();
+        left.children().put_child_elem(1, Augmented::new(Weight(4))).unwrap();
+    }
+    assert_eq!(*tree.read_root().unwrap().aggregate(), 10);
+    assert_eq!(*tree.read_root().unwrap().child(0).unwrap().unwrap().aggregate(), 6);
+    {
+        let mut op = tree.operation();
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.borrow_child_write(1).unwrap().unwrap().elem().0 = 30;
+        let mut left = children.borrow_child_write(0).unwrap().unwrap();
+        left.children().take_child(1).unwrap().unwrap();
+    }
+    assert_eq!(*tree.read_root().unwrap().aggregate(), 33);
+    assert_eq!(*tree.read_root().unwrap().child(0).unwrap().unwrap().aggregate(), 2);
+    {
+        let mut op = tree.operation();
+        op.traverse_root().unwrap().rotate_right().unwrap();
+    }
+    let root = tree.read_root().unwrap();
+    assert_eq!(*root.aggregate(), 33);
+    assert_eq!(*root.child(1).unwrap().unwrap().aggregate(), 31);
+
+    // changes made outside of an operation are only reflected once the aggregates are updated
+    let right = root.child(1).unwrap().unwrap().index();
+    tree.get_elem_mut(right).unwrap().0 = 10;
+    assert_eq!(*tree.read_root().unwrap().aggregate(), 33);
+    tree.update_aggregates();
+    assert_eq!(*tree.read_root().unwrap().aggregate(), 42);
+}
+
+#[test]
+fn augmented_by_branch() {
+    let mut tree = Tree::<Augmented<Leftmost>, [ChildId; 2]>::new_augmented();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(Augmented::new(Leftmost('b')));
+        let mut root = op.write_root().unwrap();
+        root.children().put_child_elem(1, Augmented::new(Leftmost('c'))).unwrap();
+        assert_eq!(*root.into_read_guard().aggregate(), 'b');
+    }
+    {
+        let mut op = tree.operation();
+        op.write_root().unwrap().children().put_child_elem(0, Augmented::new(Leftmost('a'))).unwrap();
+    }
+    assert_eq!(*tree.read_root().unwrap().aggregate(), 'a');
+}
+
+#[test]
+fn cached_shape() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(0);
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, 1).unwrap();
+        children.put_child_elem(1, 2).unwrap();
+        children.borrow_child_write(1).unwrap().unwrap().children().put_child_elem(0, 3).unwrap();
+    }
+    assert_eq!(tree.read_root().unwrap().subtree_len(), None);
+    tree.enable_shape_cache();
+    {
+        let root = tree.read_root().unwrap();
+        assert_eq!(root.subtree_len(), Some(4));
+        let grandchild = root.child(1).unwrap().unwrap().child(0).unwrap().unwrap();
+        assert_eq!(grandchild.depth(), Some(2));
+    }
+    {
+        let mut op = tree.operation();
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        let mut subtree = children.take_child(1).unwrap().unwrap();
+        assert_eq!(subtree.into_read_guard().subtree_len(), Some(2));
+        assert_eq!(subtree.into_read_guard().depth(), Some(0));
+        subtree.children().put_child_elem(1, 4).unwrap();
+        let mut left = children.borrow_child_write(0).unwrap().unwrap();
+        left.children().put_child_tree(0, subtree).unwrap();
+    }
+    {
+        let root = tree.read_root().unwrap();
+        assert_eq!(root.subtree_len(), Some(5));
+        assert!(root.child(1).unwrap().is_none());
+        let moved = root.child(0).unwrap().unwrap().child(0).unwrap().unwrap();
+        assert_eq!(moved.subtree_len(), Some(3));
+        assert_eq!(moved.child(1).unwrap().unwrap().depth(), Some(3));
+    }
+    {
+        let mut op = tree.operation();
+        op.traverse_root().unwrap().rotate_right().unwrap();
+    }
+    let root = tree.read_root().unwrap();
+    assert_eq!(*root, 1);
+    assert_eq!(root.subtree_len(), Some(5));
+    assert_eq!(root.child(0).unwrap().unwrap().depth(), Some(1));
+    assert_eq!(root.child(0).unwrap().unwrap().child(1).unwrap().unwrap().depth(), Some(2));
+    let demoted = root.child(1).unwrap().unwrap();
+    assert_eq!((*demoted, demoted.subtree_len(), demoted.depth()), (0, Some(1), Some(1)));
+}
+
+#[test]
+fn ancestry_queries() {
+    fn build<'op, 'node, 't>(children: &mut ChildWriteGuard<'op, 'node, 't, i32, [ChildId; 2]>, elem: i32) {
+        for branch in 0..2 {
+            let child_elem = elem * 2 + branch as i32;
+            if child_elem < 24 && child_elem % 5 != 0 {
+                children.put_child_elem(branch, child_elem).unwrap();
+                build(&mut children.borrow_child_write(branch).unwrap().unwrap().children(), child_elem);
+            }
+        }
+    }
+    fn collect<'tree>(node: NodeReadGuard<'tree, i32, [ChildId; 2]>, out: &mut Vec<(i32, NodeIndex)>) {
+        out.push((*node, node.index()));
+        for branch in 0..2 {
+            if let Some(child) = node.child(branch).unwrap() {
+                collect(child, out);
+            }
+        }
+    }
+
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(1);
+        build(&mut op.write_root().unwrap().children(), 1);
+    }
+    let mut nodes = Vec::new();
+    collect(tree.read_root().unwrap(), &mut nodes);
+    let index_of = |elem: i32| nodes.iter().find(|&&(e, _)| e == elem).unwrap().1;
+
+    assert_eq!(tree.depth(index_of(1)), Some(0));
+    assert_eq!(tree.depth(index_of(13)), Some(3));
+    assert_eq!(tree.lca(index_of(12), index_of(13)), Some(index_of(6)));
+    assert_eq!(tree.lca(index_of(3), index_of(13)), Some(index_of(3)));
+    assert!(tree.is_ancestor(index_of(3), index_of(13)));
+    assert!(!tree.is_ancestor(index_of(13), index_of(3)));
+    let path: Vec<i32> = tree.path_between(index_of(9), index_of(13)).unwrap().into_iter()
+        .map(|index| nodes.iter().find(|&&(_, i)| i == index).unwrap().0)
+        .collect();
+    assert_eq!(path, vec![9, 4, 2, 1, 3, 6, 13]);
+
+    // the lifting index agrees with the parent-walking queries, with or without a shape cache
+    for &cache in &[false, true] {
+        if cache {
+            tree.enable_shape_cache();
+        }
+        let ancestors = tree.ancestor_index();
+        for &(_, a) in &nodes {
+            assert_eq!(ancestors.depth(a), tree.depth(a));
+            for &(_, b) in &nodes {
+                assert_eq!(ancestors.is_ancestor(a, b), tree.is_ancestor(a, b));
+                assert_eq!(ancestors.lca(a, b), tree.lca(a, b));
+                assert_eq!(ancestors.path_between(a, b), tree.path_between(a, b));
+            }
+        }
+    }
+}
+
+#[test]
+fn transplant_between_trees() {
+    let mut a = Tree::<String, [ChildId; 2]>::new();
+    let mut b = Tree::<String, [ChildId; 2]>::new();
+    b.enable_shape_cache();
+    {
+        let mut op = a.operation();
+        op.put_root_elem("a".to_string());
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, "b".to_string()).unwrap();
+        children.put_child_elem(1, "c".to_string()).unwrap();
+        let mut c = children.borrow_child_write(1).unwrap().unwrap();
+        c.children().put_child_elem(1, "d".to_string()).unwrap();
+    }
+    {
+        let a_op = a.operation();
+        let mut b_op = b.operation();
+        b_op.put_root_elem("x".to_string());
+
+        // copy the whole of a, then move c out of a
+        let copy = a_op.read_root().unwrap().clone_subtree_into(&b_op);
+        let moved = {
+            let mut a_root = a_op.take_root().unwrap();
+            let subtree = a_root.children().take_child(1).unwrap().unwrap();
+            a_op.try_put_root_tree(a_root).unwrap();
+            b_op.transplant_from(subtree)
+        };
+        assert_eq!(moved.into_read_guard().subtree_len(), Some(2));
+
+        let mut b_root = b_op.take_root().unwrap();
+        {
+            let mut children = b_root.children();
+            children.put_child_tree(0, copy).unwrap();
+            children.put_child_tree(1, moved).unwrap();
+        }
+        b_op.try_put_root_tree(b_root).unwrap();
+    }
+
+    let a_root = a.read_root().unwrap();
+    assert_eq!(*a_root, "a");
+    assert!(a_root.child(1).unwrap().is_none());
+    assert_eq!(unsafe { (&*a.nodes.get()).len() }, 2);
+
+    let b_root = b.read_root().unwrap();
+    assert_eq!(b_root.subtree_len(), Some(7));
+    let copy = b_root.child(0).unwrap().unwrap();
+    assert_eq!(*copy.child(1).unwrap().unwrap().child(1).unwrap().unwrap(), "d");
+    assert_eq!(copy.child(1).unwrap().unwrap().child(1).unwrap().unwrap().depth(), Some(3));
+    let moved = b_root.child(1).unwrap().unwrap();
+    assert_eq!((&**moved, &**moved.child(1).unwrap().unwrap()), ("c", "d"));
+}
+
+#[test]
+fn split_off_and_graft() {
+    let mut tree = Tree::<Augmented<Weight>, [ChildId; 2]>::new_augmented();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(Augmented::new(Weight(1)));
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, Augmented::new(Weight(2))).unwrap();
+        children.put_child_elem(1, Augmented::new(Weight(4))).unwrap();
+        let mut right = children.borrow_child_write(1).unwrap().unwrap();
+        right.children().put_child_elem(0, Augmented::new(Weight(8))).unwrap();
+    }
+
+    let mut branch = {
+        let op = tree.operation();
+        let mut root = op.take_root().unwrap();
+        let subtree = root.children().take_child(1).unwrap().unwrap();
+        op.try_put_root_tree(root).unwrap();
+        op.split_off(subtree)
+    };
+    assert_eq!(*tree.read_root().unwrap().aggregate(), 3);
+    assert_eq!(*branch.read_root().unwrap().aggregate(), 12);
+    assert_eq!(unsafe { (&*branch.nodes.get()).len() }, 2);
+    {
+        let mut op = branch.operation();
+        op.write_root().unwrap().elem().0 = 16;
+    }
+
+    {
+        let op = tree.operation();
+        let grafted = op.graft(branch).unwrap();
+        let mut root = op.take_root().unwrap();
+        root.children().put_child_tree(0, grafted).unwrap();
+        op.try_put_root_tree(root).unwrap();
+    }
+    let root = tree.read_root().unwrap();
+    assert_eq!(*root.aggregate(), 25);
+    assert_eq!(root.child(0).unwrap().unwrap().child(0).unwrap().unwrap().0, 8);
+
+    let mut empty = Tree::<i32, [ChildId; 2]>::new();
+    let op = empty.operation();
+    assert!(op.graft(Tree::new()).is_none());
+}
+
+#[test]
+fn clone_subtrees_in_place() {
+    let mut tree = Tree::<Vec<i32>, [ChildId; 3]>::new();
+    tree.enable_shape_cache();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(vec![0]);
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, vec![1]).unwrap();
+        children.borrow_child_write(0).unwrap().unwrap().children().put_child_elem(2, vec![1, 2]).unwrap();
+
+        assert_eq!(children.clone_child_to(0, 1), Ok(false));
+        assert_eq!(children.clone_child_to(2, 1), Err(Error::ChildNotFound(2)));
+        assert_eq!(children.clone_child_to(0, 3), Err(Error::InvalidBranchIndex(3)));
+        children.borrow_child_write(1).unwrap().unwrap().elem().push(10);
+    }
+    {
+        let op = tree.operation();
+        let copy = op.read_root().unwrap().child(1).unwrap().unwrap().clone_detached(&op);
+        let mut root = op.take_root().unwrap();
+        assert_eq!(root.children().put_child_tree(2, copy).unwrap(), false);
+        op.try_put_root_tree(root).unwrap();
+    }
+    let root = tree.read_root().unwrap();
+    assert_eq!(root.subtree_len(), Some(7));
+    assert_eq!(*root.child(0).unwrap().unwrap(), vec![1]);
+    for branch in 1..3 {
+        let copy = root.child(branch).unwrap().unwrap();
+        assert_eq!(*copy, vec![1, 10]);
+        assert_eq!(*copy.child(2).unwrap().unwrap(), vec![1, 2]);
+        assert_eq!(copy.child(2).unwrap().unwrap().depth(), Some(2));
+    }
+}
+
+#[test]
+fn map_preserves_layout() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(1);
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, 2).unwrap();
+        children.put_child_elem(1, -3).unwrap();
+        children.borrow_child_write(0).unwrap().unwrap().children().put_child_elem(1, 4).unwrap();
+    }
+    let grandchild = tree.read_root().unwrap().child(0).unwrap().unwrap().child(1).unwrap().unwrap().index();
+
+    let strings = tree.map_ref(|&elem| elem.to_string());
+    assert_eq!(*tree.read_root().unwrap(), 1);
+    assert_eq!(*strings.read_root().unwrap().child(1).unwrap().unwrap(), "-3");
+    assert!(strings.is_ancestor(strings.read_root().unwrap().index(), grandchild));
+
+    let mut doubled = tree.map(|elem| elem * 2);
+    assert_eq!(*doubled.get_elem_mut(grandchild).unwrap(), 8);
+
+    assert_eq!(doubled.try_map(|elem| if elem > 0 { Ok(elem as u32) } else { Err(elem) }).err(), Some(-6));
+
+    let mut chain = Tree::<usize, [ChildId; 2]>::new();
+    {
+        let mut op = chain.operation();
+        op.put_root_elem(0);
+        let mut node = op.write_root().unwrap();
+        for i in 1..100 {
+            node.children().put_child_elem(0, i).unwrap();
+            node = node.into_split().1.into_child_write(0).unwrap().unwrap();
+        }
+    }
+    let chain = chain.map(|elem| elem + 1);
+    assert_eq!(unsafe { (&*chain.nodes.get()).chunk_lens() }, vec![100]);
+}
+
+#[test]
+fn diff_and_zip() {
+    fn build(elems: &[(&[usize], char)]) -> Tree<char, [ChildId; 2]> {
+        let mut tree = Tree::new();
+        {
+            let mut op = tree.operation();
+            for &(path, elem) in elems {
+                match path.split_last() {
+                    None => {
+                        op.put_root_elem(elem);
+                    },
+                    Some((&branch, parent_path)) => {
+                        let mut node = op.write_root().unwrap();
+                        for &b in parent_path {
+                            node = node.into_split().1.into_child_write(b).unwrap().unwrap();
+                        }
+                        node.children().put_child_elem(branch, elem).unwrap();
+                    },
+                };
+            }
+        }
+        tree
+    }
+
+    let old = build(&[(&[], 'a'), (&[0], 'b'), (&[1], 'c'), (&[1, 0], 'd'), (&[1, 0, 1], 'e')]);
+    let new = build(&[(&[], 'a'), (&[0], 'x'), (&[0, 1], 'd'), (&[0, 1, 1], 'e'), (&[1], 'c'), (&[1, 1], 'f')]);
+
+    let ops: Vec<DiffOp> = old.diff(&new).collect();
+    assert_eq!(ops, vec![
+        DiffOp::Changed { path: "0".parse().unwrap() },
+        DiffOp::Moved { from: "1/0".parse().unwrap(), to: "0/1".parse().unwrap() },
+        DiffOp::Inserted { path: "1/1".parse().unwrap() },
+    ]);
+    assert_eq!(old.diff(&old).count(), 0);
+
+    let zipped: Vec<(Option<&char>, Option<&char>)> = old.zip(&new).collect();
+    assert_eq!(zipped, vec![
+        (Some(&'a'), Some(&'a')),
+        (Some(&'b'), Some(&'x')),
+        (None, Some(&'d')),
+        (None, Some(&'e')),
+        (Some(&'c'), Some(&'c')),
+        (Some(&'d'), None),
+        (Some(&'e'), None),
+        (None, Some(&'f')),
+    ]);
+}
+
+#[test]
+fn fold_expression_tree() {
+    enum Expr {
+        Num(i64),
+        Add,
+        Mul,
+    }
+
+    let mut tree = Tree::<Expr, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(Expr::Add);
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, Expr::Num(2)).unwrap();
+        children.put_child_elem(1, Expr::Mul).unwrap();
+        let mut product = children.borrow_child_write(1).unwrap().unwrap();
+        let mut factors = product.children();
+        factors.put_child_elem(0, Expr::Num(3)).unwrap();
+        factors.put_child_elem(1, Expr::Num(4)).unwrap();
+    }
+    let value = tree.fold(|expr, operands: &[Option<i64>]| match expr {
+        &Expr::Num(n) => n,
+        &Expr::Add => operands[0].unwrap() + operands[1].unwrap(),
+        &Expr::Mul => operands[0].unwrap() * operands[1].unwrap(),
+    });
+    assert_eq!(value, Some(14));
+
+    // a degenerate tree, which is folded without recursion
+    let mut deep = Tree::<u32, [ChildId; 2]>::new();
+    {
+        let mut op = deep.operation();
+        op.put_root_elem(0);
+        let mut node = op.write_root().unwrap();
+        for i in 1..2_000 {
+            let (_, mut children) = node.into_split();
+            children.put_child_elem(1, i).unwrap();
+            node = children.into_child_write(1).unwrap().unwrap();
+        }
+    }
+    let height = deep.fold(|_, children: &[Option<usize>]| {
+        children.iter().map(|child| child.unwrap_or(0)).max().unwrap() + 1
+    });
+    assert_eq!(height, Some(2_000));
+    assert_eq!(Tree::<u32, [ChildId; 2]>::new().fold(|_, _: &[Option<()>]| ()), None);
+}
+
+#[test]
+fn retain_and_prune() {
+    fn build(children: &mut ChildWriteGuard<i32, [ChildId; 3]>, elem: i32, depth: usize) {
+        if depth < 4 {
+            for branch in 0..3 {
+                let child_elem = elem * 3 + branch as i32;
+                children.put_child_elem(branch, child_elem).unwrap();
+                build(&mut children.borrow_child_write(branch).unwrap().unwrap().children(), child_elem, depth + 1);
+            }
+        }
+    }
+    fn len(tree: &Tree<i32, [ChildId; 3]>) -> usize {
+        tree.fold(|_, children: &[Option<usize>]| children.iter().map(|len| len.unwrap_or(0)).sum::<usize>() + 1)
+            .unwrap_or(0)
+    }
+
+    let mut tree = Tree::<i32, [ChildId; 3]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(1);
+        build(&mut op.write_root().unwrap().children(), 1, 0);
+    }
+    tree.enable_shape_cache();
+    assert_eq!(len(&tree), 121);
+
+    {
+        let mut op = tree.operation();
+        let mut visited = 0;
+        op.retain(|_, path| {
+            visited += 1;
+            path.last() != Some(&1)
+        });
+        // the removed subtrees' descendants are never visited
+        assert_eq!(visited, 1 + 3 + 6 + 12 + 24);
+    }
+    assert_eq!(len(&tree), 31);
+    assert_eq!(tree.read_root().unwrap().subtree_len(), Some(31));
+    assert_eq!(unsafe { (&*tree.nodes.get()).len() }, 31);
+
+    {
+        let mut op = tree.operation();
+        op.prune_depth(2);
+    }
+    assert_eq!(len(&tree), 7);
+    assert_eq!(unsafe { (&*tree.nodes.get()).len() }, 7);
+
+    {
+        let mut op = tree.operation();
+        op.retain(|&elem, _| elem != 1);
+    }
+    assert!(tree.read_root().is_none());
+}
+
+#[test]
+fn branch_paths() {
+    let mut tree = Tree::<&'static str, [ChildId; 3]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem("root");
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(2, "2").unwrap();
+        children.borrow_child_write(2).unwrap().unwrap().children().put_child_elem(0, "2/0").unwrap();
+
+        let path: BranchPath = "2/0".parse().unwrap();
+        *op.get_mut(&path).unwrap().elem() = "renamed";
+        assert!(op.get_mut(&"2/1".parse().unwrap()).is_none());
+        assert!(op.get_mut(&"3".parse().unwrap()).is_none());
+    }
+
+    let path = BranchPath::from(vec![2, 0]);
+    assert_eq!(path.to_string(), "2/0");
+    assert_eq!(BranchPath::root().to_string(), "");
+    assert_eq!("".parse::<BranchPath>().unwrap(), BranchPath::root());
+    assert!("2//0".parse::<BranchPath>().is_err());
+    assert_eq!(path.parent(), Some(BranchPath::from(vec![2])));
+
+    let node = tree.get(&path).unwrap();
+    assert_eq!(*node, "renamed");
+    assert_eq!(node.path(), path);
+    assert_eq!(tree.read_root().unwrap().path(), BranchPath::root());
+    assert!(tree.get(&"0".parse().unwrap()).is_none());
+}
+
+#[test]
+fn find_and_descend() {
+    // an unbalanced search tree
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        for &key in &[50, 30, 70, 20, 40, 60, 45] {
+            let missing = match op.read_root() {
+                None => None,
+                Some(root) => {
+                    let (parent, branch) = root.descend(|&elem| if key < elem { Some(0) } else { Some(1) });
+                    Some((parent.path(), branch.unwrap()))
+                },
+            };
+            match missing {
+                None => {
+                    op.put_root_elem(key);
+                },
+                Some((path, branch)) => {
+                    op.get_mut(&path).unwrap().children().put_child_elem(branch, key).unwrap();
+                },
+            };
+        }
+    }
+
+    let search = |key: i32| tree.descend(|&elem| if key == elem { None } else if key < elem { Some(0) } else { Some(1) });
+    let (found, missing) = search(45).unwrap();
+    assert_eq!((*found, missing), (45, None));
+    assert_eq!(found.path().to_string(), "0/1/1");
+    let (parent, missing) = search(65).unwrap();
+    assert_eq!((*parent, missing), (60, Some(1)));
+
+    assert_eq!(*tree.find(|&elem| elem % 20 == 0).unwrap(), 20);
+    assert!(tree.find(|&elem| elem > 100).is_none());
+    let found: Vec<i32> = tree.find_all(|&elem| elem % 20 != 0).map(|node| *node).collect();
+    assert_eq!(found, vec![50, 30, 45, 70]);
+}
+
+#[test]
+fn descend_mut_entries() {
+    let mut tree = Tree::<(i32, u32), [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        for &key in &[5, 2, 8, 2, 7, 5, 2] {
+            let guide = |&(elem, _): &(i32, u32)| if key == elem { None } else if key < elem { Some(0) } else { Some(1) };
+            op.descend_mut(guide).or_insert((key, 0)).elem().1 += 1;
+        }
+        match op.descend_mut(|&(elem, _)| if elem < 6 { Some(1) } else { Some(0) }) {
+            Entry::Vacant(vacant) => {
+                assert_eq!(vacant.branch(), Some(0));
+                vacant.insert((6, 1));
+            },
+            Entry::Occupied(_) => panic!("expected a vacant child"),
+        };
+    }
+    let in_order = tree.fold(|&elem, children: &[Option<Vec<(i32, u32)>>]| {
+        let mut elems = children[0].clone().unwrap_or_default();
+        elems.push(elem);
+        elems.extend(children[1].clone().unwrap_or_default());
+        elems
+    });
+    assert_eq!(in_order, Some(vec![(2, 3), (5, 2), (6, 1), (7, 1), (8, 1)]));
+}
+
+#[test]
+fn split_children_array() {
+    let mut tree = Tree::<i32, [ChildId; 3]>::new();
+    let mut op = tree.operation();
+    op.put_root_elem(0);
+    {
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, 1).unwrap();
+        children.put_child_elem(2, 3).unwrap();
+    }
+    {
+        let (root, [first, second, third]) = op.write_root().unwrap().into_split_children();
+        assert!(second.is_none());
+        let (mut first, mut third) = (first.unwrap(), third.unwrap());
+        *root += *first.elem() + *third.elem();
+        first.children().put_child_elem(1, 10).unwrap();
+        *third.elem() *= 2;
+    }
+    let root = op.read_root().unwrap();
+    assert_eq!(*root, 4);
+    assert_eq!(*root.child(2).unwrap().unwrap(), 6);
+    assert_eq!(*root.child(0).unwrap().unwrap().child(1).unwrap().unwrap(), 10);
+}
+
+#[test]
+fn get_many_mut_by_paths() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    let mut op = tree.operation();
+    op.put_root_elem(0);
+    {
+        let mut root = op.write_root().unwrap();
+        for &(path, elem) in &[("0", 1), ("1", 2), ("0/1", 3), ("1/0", 4), ("1/0/0", 5)] {
+            let path: BranchPath = path.parse().unwrap();
+            let parent = path.parent().unwrap();
+            let mut parent = root.get_many_mut(&[parent]).unwrap().unwrap();
+            parent[0].children().put_child_elem(*path.last().unwrap(), elem).unwrap();
+        }
+
+        let paths = ["0/1".parse().unwrap(), "1/0/0".parse().unwrap(), "1".parse().unwrap()];
+        assert_eq!(root.get_many_mut(&paths).err(), Some(Overlap(1, 2)));
+        let paths: [BranchPath; 2] = ["0".parse().unwrap(), "0".parse().unwrap()];
+        assert_eq!(root.get_many_mut(&paths).err(), Some(Overlap(0, 1)));
+        let paths: [BranchPath; 2] = ["0/0".parse().unwrap(), "1".parse().unwrap()];
+        assert!(root.get_many_mut(&paths).unwrap().is_none());
+
+        let paths: [BranchPath; 2] = ["0/1".parse().unwrap(), "1/0/0".parse().unwrap()];
+        let [mut a, mut b] = root.get_many_mut(&paths).unwrap().unwrap();
+        ::std::mem::swap(a.elem(), b.elem());
+    }
+    assert_eq!(*op.read_root().unwrap().child(0).unwrap().unwrap().child(1).unwrap().unwrap(), 5);
+    let node = op.read_root().unwrap().child(1).unwrap().unwrap().child(0).unwrap().unwrap().child(0).unwrap().unwrap();
+    assert_eq!(*node, 3);
+}
+
+#[test]
+fn display_tree_drawing() {
+    let mut tree = Tree::<char, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem('a');
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, 'b').unwrap();
+        children.put_child_elem(1, 'c').unwrap();
+        let mut b = children.borrow_child_write(0).unwrap().unwrap();
+        b.children().put_child_elem(1, 'd').unwrap();
+    }
+
+    assert_eq!(tree.display_tree().to_string(), "\
+a
+├── 0: b
+│   ├── 0: ∅
+│   └── 1: d
+│       ├── 0: ∅
+│       └── 1: ∅
+└── 1: c
+    ├── 0: ∅
+    └── 1: ∅
+");
+    assert_eq!(tree.display_tree().omit_empty(true).ascii(true).to_string(), "\
+a
+|-- 0: b
+|   `-- 1: d
+`-- 1: c
+");
+    assert_eq!(tree.display_tree().omit_empty(true).max_depth(1).to_string(), "\
+a
+├── 0: b
+│   └── ...
+└── 1: c
+");
+    let upper = tree.display_tree_with(|elem, f| write!(f, "{}", elem.to_ascii_uppercase())).omit_empty(true);
+    assert_eq!(upper.to_string(), "A\n├── 0: B\n│   └── 1: D\n└── 1: C\n");
+    assert_eq!(tree.read_root().unwrap().child(0).unwrap().unwrap().display_tree().omit_empty(true).to_string(), "b\n└── 1: d\n");
+}
+
+#[test]
+fn dot_export() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(1);
+        let mut root = op.write_root().unwrap();
+        root.children().put_child_elem(1, 2).unwrap();
+    }
+    let mut out = String::new();
+    tree.to_dot(&mut out).unwrap();
+    assert_eq!(out, r##"digraph tree {
+    node [shape=box];
+    n0 [label="#0\n1"];
+    n1 [label="#1\n2"];
+    root [shape=point];
+    root -> n0;
+    n0 -> n1 [label="1"];
+    n1 -> n0 [label="1", style=dashed, color=gray, constraint=false];
+}
+"##);
+
+    let mut op = tree.operation();
+    op.write_root().unwrap().children().put_child_elem(0, 3).unwrap();
+    op.write_root().unwrap().children().put_child_elem(0, 4).unwrap();
+    let detached = [op.new_detached(5), op.new_detached(6), op.new_detached(7)];
+    let mut dot = String::new();
+    op.to_dot_with(&mut dot, DotOptions { garbage: true, chunks: true }).unwrap();
+    assert!(dot.contains("subgraph cluster_0 {"));
+    assert!(dot.contains("subgraph cluster_1 {\n        label=\"chunk 1\";\n        style=dashed;\n        n6"));
+    assert!(dot.contains(r##"n4 [label="#4\n5", style=dotted];"##));
+    assert!(dot.contains(r##"n2 [label="#2", style=filled, fillcolor=gray];"##));
+    assert!(dot.contains(r##"n0 -> n3 [label="0"];"##));
+    drop(detached);
+}
+
+#[test]
+fn dot_export_hides_garbage_descendants() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    let mut op = tree.operation();
+    op.put_root_elem(1);
+    {
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, 2).unwrap();
+        children.borrow_child_write(0).unwrap().unwrap().children().put_child_elem(1, 3).unwrap();
+        children.take_child(0).unwrap().unwrap();
+    }
+
+    // only the root of the dropped subtree is marked as garbage, but its child is garbage too
+    let mut dot = String::new();
+    op.to_dot_with(&mut dot, DotOptions::default()).unwrap();
+    assert_eq!(dot, r##"digraph tree {
+    node [shape=box];
+    n0 [label="#0\n1"];
+    root [shape=point];
+    root -> n0;
+}
+"##);
+
+    let mut dot = String::new();
+    op.to_dot_with(&mut dot, DotOptions { garbage: true, chunks: false }).unwrap();
+    assert!(dot.contains(r##"n2 [label="#2\n3", style=filled, fillcolor=gray];"##));
+    assert!(dot.contains(r##"n2 -> n1 [label="1", style=dashed, color=gray, constraint=false];"##));
+}
+
+#[test]
+fn validate_structure() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    tree.enable_shape_cache();
+    assert_eq!(tree.validate(), Ok(()));
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(1);
+        {
+            let mut root = op.write_root().unwrap();
+            let mut children = root.children();
+            children.put_child_elem(0, 2).unwrap();
+            children.put_child_elem(1, 3).unwrap();
+            children.borrow_child_write(1).unwrap().unwrap().children().put_child_elem(0, 4).unwrap();
+            children.put_child_elem(0, 5).unwrap();
+        }
+        let mut detached = op.new_detached(6);
+        detached.children().put_child_elem(0, 7).unwrap();
+        assert_eq!(op.validate(), Ok(()));
+        // the dropped subtree's descendants are garbage, even before they are collected
+        drop(detached);
+        assert_eq!(op.validate(), Ok(()));
+    }
+    assert_eq!(tree.validate(), Ok(()));
+
+    unsafe {
+        let nodes = &*tree.nodes.get();
+        let set_parent = |index: usize, parent_id: ParentId| match &*nodes[index].get() {
+            &Node::Present {
+                ref parent,
+                ..
+            } => parent.replace(parent_id),
+            &Node::Garbage { .. } => unreachable!(),
+        };
+        let root = tree.root.get().unwrap();
+        let child = tree.read_root().unwrap().child(0).unwrap().unwrap().index;
+
+        let old = set_parent(child, ParentId::Some {
+            parent_index: root,
+            this_branch: 1,
+        });
+        assert_eq!(tree.validate(), Err(Corruption::ChildLink {
+            parent: root,
+            branch: 0,
+            child,
+        }));
+        assert_eq!(tree.validate().unwrap_err().to_string(), format!(
+            "node {} links to child {} at branch 0, which does not link back", root, child));
+        let set_root_child = |index: Option<usize>| match &*nodes[root].get() {
+            &Node::Present {
+                ref children,
+                ..
+            } => (&mut *children.get())[0] = ChildId {
+                index,
+            },
+            &Node::Garbage { .. } => unreachable!(),
+        };
+        set_root_child(None);
+        set_parent(child, ParentId::Detached);
+        assert_eq!(tree.validate(), Err(Corruption::Detached {
+            index: child,
+        }));
+        set_root_child(Some(child));
+        set_parent(child, old);
+        assert_eq!(tree.validate(), Ok(()));
+
+        (&mut *tree.shapes.get())[child].depth = 3;
+        assert_eq!(tree.validate(), Err(Corruption::Shape {
+            index: child,
+        }));
+    }
+}
+
+fn model_target() -> impl Strategy<Value = model::Target> {
+    (0..8usize, prop::collection::vec(0..3usize, 0..4))
+        .prop_map(|(subtree, path)| model::Target {
+            subtree,
+            path,
+        })
+}
+
+fn model_op() -> impl Strategy<Value = model::Op> {
+    use model::Op;
+
+    prop_oneof![
+        2 => (model_target(), 0..3usize).prop_map(|(target, branch)| Op::PutChildElem(target, branch)),
+        1 => (model_target(), 0..3usize).prop_map(|(target, branch)| Op::TakeChild(target, branch)),
+        1 => (model_target(), 0..3usize, 0..8usize)
+            .prop_map(|(target, branch, other)| Op::PutChildTree(target, branch, other)),
+        1 => model_target().prop_map(Op::Detach),
+        1 => (0..8usize).prop_map(Op::Drop),
+        1 => (0..8usize).prop_map(Op::IntoElem),
+        1 => Just(Op::TakeRoot),
+        1 => (0..8usize).prop_map(Op::PutRootTree),
+        2 => Just(Op::NewDetached),
+        1 => (0..8usize).prop_map(Op::Collect),
+    ]
+}
+
+proptest! {
+    // under miri, see the readme, only run a few cases
+    #![proptest_config(ProptestConfig {
+        cases: if cfg!(miri) { 8 } else { 256 },
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn random_operations_match_model(ops in prop::collection::vec(model_op(), 0..200), shape_cache in any::<bool>()) {
+        model::check(&ops, shape_cache);
+    }
+}
+
+#[test]
+fn unified_errors() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(0);
+        op.write_root().unwrap().children().put_child_elem(1, 1).unwrap();
+
+        let trav = op.traverse_root().unwrap();
+        assert_eq!(trav.seek_parent(), Err(Error::NoParent(NoParent::Root)));
+        assert_eq!(trav.seek_child(0), Err(Error::ChildNotFound(0)));
+        assert_eq!(trav.seek_child(2), Err(Error::InvalidBranchIndex(2)));
+        assert_eq!(trav.detach_child(0).err(), Some(Error::ChildNotFound(0)));
+        trav.seek_child(1).unwrap();
+        assert_eq!(*trav, 1);
+
+        assert_eq!(op.try_descend_mut(|_| Some(2)).err(), Some(Error::InvalidBranchIndex(2)));
+    }
+
+    assert_eq!(tree.try_descend(|_| Some(3)).err(), Some(Error::InvalidBranchIndex(3)));
+    let (node, missing) = tree.try_descend(|_| Some(1)).unwrap().unwrap();
+    assert_eq!((*node, missing), (1, Some(1)));
+
+    let err: &dyn std::error::Error = &Error::ChildNotFound(1);
+    assert_eq!(err.to_string(), "no child at branch 1");
+    assert_eq!(Error::WrongChildrenNum { expected_num: 2, actual_num: 3 }.to_string(),
+               "expected room for 2 children, found 3");
+}
+
+#[test]
+fn get_by_node_index() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(0);
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, 1).unwrap();
+        children.put_child_elem(1, 2).unwrap();
+        children.borrow_child_write(1).unwrap().unwrap().children().put_child_elem(0, 3).unwrap();
+    }
+
+    // a side table keyed by node index
+    let mut labels = std::collections::HashMap::new();
+    for node in tree.find_all(|_| true) {
+        labels.insert(node.index(), format!("node {}", *node));
+    }
+    let index_of = |elem: i32| tree.find(|&e| e == elem).unwrap().index();
+    let (one, two, three) = (index_of(1), index_of(2), index_of(3));
+    assert_eq!(*tree.get(three).unwrap(), 3);
+    assert_eq!(labels[&three], "node 3");
+
+    {
+        let mut op = tree.operation();
+        *op.get_mut(one).unwrap().elem() += 10;
+        *op.get_elem_mut(three).unwrap() += 10;
+        assert_eq!(op.get(&BranchPath::from(vec![1, 0])).unwrap().index(), three);
+
+        // nodes in detached subtrees, and garbage, are not available
+        let detached = op.take_root().unwrap();
+        assert!(op.get(one).is_none());
+        op.try_put_root_tree(detached).unwrap();
+        assert_eq!(*op.get(one).unwrap(), 11);
+        op.write_root().unwrap().children().put_child_elem(1, 4).unwrap();
+        assert!(op.get(two).is_none());
+        assert!(op.get(three).is_none());
+    }
+    assert_eq!(tree.get(one).map(|node| *node), Some(11));
+}
+
+#[test]
+fn traverse_from_stale_index() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(0);
+        let mut root = op.write_root().unwrap();
+        let mut children = root.children();
+        children.put_child_elem(0, 1).unwrap();
+        children.put_child_elem(1, 2).unwrap();
+    }
+    let one = tree.find(|&elem| elem == 1).unwrap().index();
+    let two = tree.find(|&elem| elem == 2).unwrap().index();
+    assert_eq!(*tree.traverse_read_from(two).unwrap(), 2);
+
+    {
+        let mut op = tree.operation();
+        assert_eq!(*op.traverse_from(two).unwrap(), 2);
+
+        // the node is garbage, but not yet collected
+        op.write_root().unwrap().children().take_child(1).unwrap().unwrap();
+        assert!(op.traverse_from(two).is_none());
+        assert_eq!(*op.traverse_from(one).unwrap(), 1);
+        op.write_root().unwrap().children().take_child(0).unwrap().unwrap();
+    }
+
+    // the nodes have been collected
+    assert!(tree.traverse_read_from(one).is_none());
+    assert!(tree.traverse_read_from(two).is_none());
+}
@@ -0,0 +1,235 @@
+//! Moving and copying subtrees between trees.
+
+use {Tree, TreeOperation, Node, ParentId, ChildId, NodeOwnedGuard, NodeReadGuard, ChildWriteGuard};
+use {Error, new_child_array, subtree_pre_order};
+
+use core::array::FixedSizeArray;
+use std::cell::{UnsafeCell, Cell};
+use std::mem;
+use std::vec::Vec;
+
+/// The elements of a subtree in pre-order, each paired with the position of its parent in that
+/// order and its branch index, except for the subtree root.
+type Gathered<T> = Vec<(T, Option<(usize, usize)>)>;
+
+/// Gather the elements of the subtree rooted at a present node, in pre-order, extracting each
+/// element from its node with the given function.
+unsafe fn gather_subtree<T, C, F>(tree: &Tree<T, C>, root_index: usize, mut extract: F) -> Gathered<T>
+    where C: FixedSizeArray<ChildId>, F: FnMut(&UnsafeCell<Node<T, C>>) -> T {
+
+    let nodes = &*tree.nodes.get();
+    let mut gathered = Vec::new();
+    // the positions of the current node's ancestors, by depth
+    let mut ancestors: Vec<usize> = Vec::new();
+    for (index, depth) in subtree_pre_order(tree, root_index) {
+        ancestors.truncate(depth);
+        let parent = match &*nodes[index].get() {
+            &Node::Present {
+                ref parent,
+                ..
+            } => match parent.get() {
+                ParentId::Some {
+                    this_branch,
+                    ..
+                } if depth > 0 => Some((ancestors[depth - 1], this_branch)),
+                _ => None,
+            },
+            &Node::Garbage { .. } => unreachable!("gathering garbage node"),
+        };
+        ancestors.push(gathered.len());
+        gathered.push((extract(&nodes[index]), parent));
+    }
+    gathered
+}
+
+/// Push gathered elements into the tree as a new, detached subtree, returning the index of its
+/// root. The new nodes are contiguous, and in pre-order.
+unsafe fn push_subtree<T, C: FixedSizeArray<ChildId>>(tree: &Tree<T, C>, gathered: Gathered<T>) -> usize {
+    let nodes = &mut *tree.nodes.get();
+    let base = nodes.len();
+    let mut is_leaf = Vec::with_capacity(gathered.len());
+    for (elem, parent) in gathered {
+        let position = is_leaf.len();
+        let parent_id = match parent {
+            Some((parent_position, this_branch)) => ParentId::Some {
+                parent_index: base + parent_position,
+                this_branch,
+            },
+            None => ParentId::Detached,
+        };
+        nodes.push(UnsafeCell::new(Node::Present {
+            elem: UnsafeCell::new(elem),
+            parent: Cell::new(parent_id),
+            children: UnsafeCell::new(new_child_array()),
+        }));
+        tree.push_shape(parent.map(|(parent_position, _)| base + parent_position));
+        is_leaf.push(true);
+
+        // link the parent to the new node
+        if let Some((parent_position, this_branch)) = parent {
+            if let &Node::Present {
+                ref children,
+                ..
+            } = &*nodes[base + parent_position].get() {
+                (&mut *children.get()).as_mut_slice()[this_branch] = ChildId {
+                    index: Some(base + position),
+                };
+            } else {
+                unreachable!("pushed subtree parent is garbage");
+            }
+            is_leaf[parent_position] = false;
+        }
+    }
+
+    if tree.shape_cache {
+        // every node comes after its parent, so a reverse pass visits children before parents
+        let shapes = &mut *tree.shapes.get();
+        for position in (1..is_leaf.len()).rev() {
+            if let &Node::Present {
+                ref parent,
+                ..
+            } = &*nodes[base + position].get() {
+                if let ParentId::Some {
+                    parent_index,
+                    ..
+                } = parent.get() {
+                    shapes[parent_index].subtree_len += shapes[base + position].subtree_len;
+                }
+            }
+        }
+    }
+
+    // updating the aggregates of the leaves also updates their ancestors
+    for (position, &leaf) in is_leaf.iter().enumerate() {
+        if leaf {
+            tree.mark_dirty(base + position);
+        }
+    }
+
+    base
+}
+
+/// Take the element out of a present node, leaving behind garbage which retains its children.
+unsafe fn take_elem<T, C: FixedSizeArray<ChildId>>(node: &UnsafeCell<Node<T, C>>) -> T {
+    (&mut *node.get()).take_elem_become_garbage()
+}
+
+/// Clone the element of a present node.
+unsafe fn clone_elem<T: Clone, C: FixedSizeArray<ChildId>>(node: &UnsafeCell<Node<T, C>>) -> T {
+    match &*node.get() {
+        &Node::Present {
+            ref elem,
+            ..
+        } => (&*elem.get()).clone(),
+        &Node::Garbage { .. } => unreachable!("cloning garbage node"),
+    }
+}
+
+impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
+    /// Move a detached subtree, which may belong to an operation on another tree, into this tree
+    /// as a detached subtree. This is an O(M) operation, where M is the number of nodes in the
+    /// subtree, and the moved nodes are stored contiguously.
+    pub fn transplant_from<'s, 'o, 'ot>(&'s self, mut subtree: NodeOwnedGuard<'o, 'ot, T, C>)
+        -> NodeOwnedGuard<'s, 'tree, T, C> {
+
+        unsafe {
+            let source = &*subtree.op.tree;
+            let gathered = gather_subtree(source, subtree.index, |node| take_elem(node));
+
+            // the source nodes are now garbage, and collecting the root will collect the rest
+            (&mut *source.garbage.get()).push(subtree.index);
+            subtree.reattached = true;
+            mem::drop(subtree);
+
+            NodeOwnedGuard {
+                op: self,
+                index: push_subtree(self.tree, gathered),
+                reattached: false,
+            }
+        }
+    }
+
+    /// Move a detached subtree out of this tree, into a new tree of its own. The new tree
+    /// maintains aggregates and caches its shape if this tree does.
+    pub fn split_off<'o>(&self, mut subtree: NodeOwnedGuard<'o, 'tree, T, C>) -> Tree<T, C> {
+        let mut tree = Tree::new();
+        tree.augment = self.tree.augment;
+        tree.shape_cache = self.tree.shape_cache;
+        unsafe {
+            let gathered = gather_subtree(self.tree, subtree.index, |node| take_elem(node));
+            (&mut *self.tree.garbage.get()).push(subtree.index);
+            subtree.reattached = true;
+            mem::drop(subtree);
+
+            let root_index = push_subtree(&tree, gathered);
+            if let &Node::Present {
+                ref parent,
+                ..
+            } = &*(&*tree.nodes.get())[root_index].get() {
+                parent.set(ParentId::Root);
+            }
+            tree.root.set(Some(root_index));
+        }
+        tree.update_aggregates();
+        tree
+    }
+
+    /// Move every node of another tree into this tree, as a detached subtree, or none if the
+    /// other tree is empty. This is the inverse of `split_off`.
+    pub fn graft<'s>(&'s self, tree: Tree<T, C>) -> Option<NodeOwnedGuard<'s, 'tree, T, C>> {
+        tree.root.get()
+            .map(|root_index| unsafe {
+                // the other tree's nodes, now garbage, are freed when it is dropped
+                let gathered = gather_subtree(&tree, root_index, |node| take_elem(node));
+                NodeOwnedGuard {
+                    op: self,
+                    index: push_subtree(self.tree, gathered),
+                    reattached: false,
+                }
+            })
+    }
+}
+
+impl<'a, T: Clone, C: FixedSizeArray<ChildId>> NodeReadGuard<'a, T, C> {
+    /// Deep-copy the subtree rooted at this node into a tree operation, which may be on another
+    /// tree, producing a detached subtree. This is an O(M) operation, where M is the number of
+    /// nodes in the subtree, and the copied nodes are stored contiguously.
+    pub fn clone_subtree_into<'o, 't>(&self, op: &'o TreeOperation<'t, T, C>) -> NodeOwnedGuard<'o, 't, T, C> {
+        unsafe {
+            let gathered = gather_subtree(self.tree, self.index, |node| clone_elem(node));
+            NodeOwnedGuard {
+                op,
+                index: push_subtree(op.tree, gathered),
+                reattached: false,
+            }
+        }
+    }
+    /// Deep-copy the subtree rooted at this node into a new detached subtree, within the
+    /// operation this guard was read from. This is `clone_subtree_into`, for the common case of
+    /// duplicating part of a tree in place.
+    pub fn clone_detached<'o, 't>(&self, op: &'o TreeOperation<'t, T, C>) -> NodeOwnedGuard<'o, 't, T, C> {
+        self.clone_subtree_into(op)
+    }
+}
+
+impl<'op, 'node, 't: 'op, T: Clone, C: FixedSizeArray<ChildId>> ChildWriteGuard<'op, 'node, 't, T, C> {
+    /// Deep-copy the child subtree at one branch to another branch, returning whether any
+    /// existing child at the destination was overridden.
+    pub fn clone_child_to(&mut self, src: usize, dst: usize) -> Result<bool, Error> {
+
+        let branch_factor = self.children().as_slice().len();
+        if dst >= branch_factor {
+            return Err(Error::InvalidBranchIndex(dst));
+        }
+        let src_index = match self.children().as_slice().get(src) {
+            Some(&ChildId { index: Some(src_index) }) => src_index,
+            Some(&ChildId { index: None }) => return Err(Error::ChildNotFound(src)),
+            None => return Err(Error::InvalidBranchIndex(src)),
+        };
+
+        let copy = unsafe {
+            NodeReadGuard::new(self.op.tree, src_index).clone_subtree_into(self.op)
+        };
+        self.put_child_tree(dst, copy)
+    }
+}
@@ -0,0 +1,307 @@
+//! Checking the structural invariants of the node vec, for debugging.
+
+use {Tree, TreeOperation, Node, ParentId, ChildId};
+
+use core::array::FixedSizeArray;
+use std::fmt::{Display, Formatter};
+use std::fmt;
+#[cfg(feature = "std")]
+use std::error;
+use std::vec::Vec;
+
+/// A violated structural invariant, found by `Tree::validate`. Nodes are identified by their
+/// index in the node vec, as in `Tree::debug_nodes` and `Tree::to_dot`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Corruption {
+    /// The tree's root does not exist, or its parent is not marked as the root.
+    Root {
+        index: usize,
+    },
+    /// A node is marked as the root, but is not the tree's root.
+    FalseRoot {
+        index: usize,
+    },
+    /// A live node links to a child which does not link back to it, at that branch.
+    ChildLink {
+        parent: usize,
+        branch: usize,
+        child: usize,
+    },
+    /// A live node links to a parent which does not link back to it, at that branch.
+    ParentLink {
+        index: usize,
+        parent: usize,
+        branch: usize,
+    },
+    /// A live node links to a child which is garbage.
+    GarbageChild {
+        parent: usize,
+        branch: usize,
+        child: usize,
+    },
+    /// A node is part of a cycle of parent links, so it is not reachable from any root.
+    Cycle {
+        index: usize,
+    },
+    /// A node is detached outside of a tree operation, so it would never be collected.
+    Detached {
+        index: usize,
+    },
+    /// The cached subtree length or depth of a live node is wrong.
+    Shape {
+        index: usize,
+    },
+}
+
+impl Display for Corruption {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &Corruption::Root {
+                index,
+            } => write!(f, "root {} is missing, or not marked as the root", index),
+            &Corruption::FalseRoot {
+                index,
+            } => write!(f, "node {} is marked as the root, but is not the root", index),
+            &Corruption::ChildLink {
+                parent,
+                branch,
+                child,
+            } => write!(f, "node {} links to child {} at branch {}, which does not link back", parent, child, branch),
+            &Corruption::ParentLink {
+                index,
+                parent,
+                branch,
+            } => write!(f, "node {} links to parent {} at branch {}, which does not link back", index, parent, branch),
+            &Corruption::GarbageChild {
+                parent,
+                branch,
+                child,
+            } => write!(f, "node {} links to garbage child {} at branch {}", parent, child, branch),
+            &Corruption::Cycle {
+                index,
+            } => write!(f, "node {} is part of a cycle of parent links", index),
+            &Corruption::Detached {
+                index,
+            } => write!(f, "node {} is detached outside of an operation", index),
+            &Corruption::Shape {
+                index,
+            } => write!(f, "node {} has a wrong cached shape", index),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl error::Error for Corruption {}
+
+/// Find which nodes are live, meaning that their ancestors lead up to the root or to the root of a
+/// detached subtree, rather than to garbage which has not yet been collected. Only the root of a
+/// dropped subtree is marked as garbage, so this follows every node's parent links, remembering
+/// the answer for each node on the way. Fails if the parent links are broken.
+pub unsafe fn live_nodes<T, C: FixedSizeArray<ChildId>>(tree: &Tree<T, C>) -> Result<Vec<bool>, Corruption> {
+    let nodes = &*tree.nodes.get();
+    let len = nodes.len();
+    let mut live: Vec<Option<bool>> = vec![None; len];
+    for index in 0..len {
+        let mut chain = Vec::new();
+        let mut curr = index;
+        let is_live = loop {
+            if let Some(is_live) = live[curr] {
+                break is_live;
+            }
+            if chain.len() > len {
+                return Err(Corruption::Cycle {
+                    index,
+                });
+            }
+            chain.push(curr);
+            match &*nodes[curr].get() {
+                &Node::Present {
+                    ref parent,
+                    ..
+                } => match parent.get() {
+                    ParentId::Some {
+                        parent_index,
+                        this_branch,
+                    } => {
+                        if parent_index >= len {
+                            return Err(Corruption::ParentLink {
+                                index: curr,
+                                parent: parent_index,
+                                branch: this_branch,
+                            });
+                        }
+                        curr = parent_index;
+                    },
+                    ParentId::Root | ParentId::Detached => break true,
+                    ParentId::Garbage => break false,
+                },
+                &Node::Garbage { .. } => break false,
+            };
+        };
+        for node in chain {
+            live[node] = Some(is_live);
+        }
+    }
+    Ok(live.into_iter().map(|is_live| is_live == Some(true)).collect())
+}
+
+/// Check the structure of a tree. Detached subtrees are only valid within an operation.
+unsafe fn validate<T, C: FixedSizeArray<ChildId>>(tree: &Tree<T, C>, in_operation: bool) -> Result<(), Corruption> {
+    let nodes = &*tree.nodes.get();
+    let len = nodes.len();
+    let parent_of = |index: usize| match &*nodes[index].get() {
+        &Node::Present {
+            ref parent,
+            ..
+        } => Some(parent.get()),
+        &Node::Garbage { .. } => None,
+    };
+
+    let live = live_nodes(tree)?;
+    let is_live = |index: usize| live[index];
+
+    if let Some(root_index) = tree.root.get() {
+        if root_index >= len || parent_of(root_index) != Some(ParentId::Root) {
+            return Err(Corruption::Root {
+                index: root_index,
+            });
+        }
+    }
+
+    // check that links agree in both directions, and collect the roots of live subtrees
+    let mut roots = Vec::new();
+    for index in (0..len).filter(|&index| is_live(index)) {
+        match parent_of(index).unwrap() {
+            ParentId::Some {
+                parent_index,
+                this_branch,
+            } => {
+                let linked_back = is_live(parent_index) && match &*nodes[parent_index].get() {
+                    &Node::Present {
+                        ref children,
+                        ..
+                    } => (&*children.get()).as_slice().get(this_branch)
+                        .map(|child_id| child_id.index == Some(index))
+                        .unwrap_or(false),
+                    &Node::Garbage { .. } => false,
+                };
+                if !linked_back {
+                    return Err(Corruption::ParentLink {
+                        index,
+                        parent: parent_index,
+                        branch: this_branch,
+                    });
+                }
+            },
+            ParentId::Root => {
+                if tree.root.get() != Some(index) {
+                    return Err(Corruption::FalseRoot {
+                        index,
+                    });
+                }
+                roots.push(index);
+            },
+            ParentId::Detached => {
+                if !in_operation {
+                    return Err(Corruption::Detached {
+                        index,
+                    });
+                }
+                roots.push(index);
+            },
+            ParentId::Garbage => unreachable!(),
+        };
+
+        if let &Node::Present {
+            ref children,
+            ..
+        } = &*nodes[index].get() {
+            for (branch, child_id) in (&*children.get()).as_slice().iter().enumerate() {
+                if let Some(child) = child_id.index {
+                    if child < len && !is_live(child) {
+                        return Err(Corruption::GarbageChild {
+                            parent: index,
+                            branch,
+                            child,
+                        });
+                    }
+                    let linked_back = child < len && parent_of(child) == Some(ParentId::Some {
+                        parent_index: index,
+                        this_branch: branch,
+                    });
+                    if !linked_back {
+                        return Err(Corruption::ChildLink {
+                            parent: index,
+                            branch,
+                            child,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    // find the depth of every live node, and an order in which parents come before children
+    let mut depths: Vec<Option<usize>> = vec![None; len];
+    let mut order = Vec::new();
+    let mut stack: Vec<(usize, usize)> = roots.into_iter().map(|root| (root, 0)).collect();
+    while let Some((index, depth)) = stack.pop() {
+        depths[index] = Some(depth);
+        order.push(index);
+        if let &Node::Present {
+            ref children,
+            ..
+        } = &*nodes[index].get() {
+            stack.extend((&*children.get()).as_slice().iter()
+                .filter_map(|child_id| child_id.index)
+                .map(|child| (child, depth + 1)));
+        }
+    }
+
+    if tree.shape_cache {
+        let shapes = &*tree.shapes.get();
+        let mut subtree_lens = vec![1; len];
+        for &index in order.iter().rev() {
+            if let Some(ParentId::Some { parent_index, .. }) = parent_of(index) {
+                subtree_lens[parent_index] += subtree_lens[index];
+            }
+        }
+        for &index in &order {
+            let shape = match shapes.get(index) {
+                Some(shape) => shape,
+                None => return Err(Corruption::Shape {
+                    index,
+                }),
+            };
+            if shape.subtree_len != subtree_lens[index] || Some(shape.depth) != depths[index] {
+                return Err(Corruption::Shape {
+                    index,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl<T, C: FixedSizeArray<ChildId>> Tree<T, C> {
+    /// Check that the node vec is a well-formed tree: the root is marked as the root, every
+    /// child and parent link agrees with the link in the other direction, no live node links to
+    /// garbage, there are no cycles, and any cached shapes are correct. This is an O(N)
+    /// operation, meant for debugging and testing, since these invariants are upheld by the
+    /// safe interface.
+    pub fn validate(&self) -> Result<(), Corruption> {
+        unsafe {
+            validate(self, false)
+        }
+    }
+}
+
+impl<'tree, T, C: FixedSizeArray<ChildId>> TreeOperation<'tree, T, C> {
+    /// Check the structure of the tree, as in `Tree::validate`, while allowing detached
+    /// subtrees and uncollected garbage.
+    pub fn validate(&self) -> Result<(), Corruption> {
+        unsafe {
+            validate(self.tree, true)
+        }
+    }
+}
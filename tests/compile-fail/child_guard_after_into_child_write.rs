@@ -0,0 +1,16 @@
+extern crate bonzai;
+
+use bonzai::{Tree, ChildId};
+
+fn main() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    let mut op = tree.operation();
+    op.put_root_elem(0);
+
+    let mut root = op.write_root().unwrap();
+    let mut children = root.children();
+    children.put_child_elem(0, 1).unwrap();
+    let child = children.into_child_write(0).unwrap().unwrap();
+    children.put_child_elem(0, 2).unwrap();
+    drop(child);
+}
@@ -0,0 +1,13 @@
+extern crate bonzai;
+
+use bonzai::{Tree, ChildId};
+
+fn main() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    let detached;
+    {
+        let op = tree.operation();
+        detached = op.new_detached(0);
+    }
+    drop(detached);
+}
@@ -0,0 +1,15 @@
+extern crate bonzai;
+
+use bonzai::{Tree, ChildId};
+
+fn assert_send<X: Send>(_: X) {}
+
+fn main() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    let mut op = tree.operation();
+    op.put_root_elem(0);
+
+    assert_send(op.new_detached(1));
+    assert_send(op.write_root().unwrap());
+    assert_send(op.traverse_root().unwrap());
+}
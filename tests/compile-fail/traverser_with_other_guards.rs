@@ -0,0 +1,14 @@
+extern crate bonzai;
+
+use bonzai::{Tree, ChildId};
+
+fn main() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    let mut op = tree.operation();
+    op.put_root_elem(0);
+
+    let traverser = op.traverse_root().unwrap();
+    let root = op.read_root().unwrap();
+    traverser.seek_child(0).unwrap();
+    drop(root);
+}
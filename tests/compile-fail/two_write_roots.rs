@@ -0,0 +1,14 @@
+extern crate bonzai;
+
+use bonzai::{Tree, ChildId};
+
+fn main() {
+    let mut tree = Tree::<i32, [ChildId; 2]>::new();
+    let mut op = tree.operation();
+    op.put_root_elem(0);
+
+    let mut a = op.write_root().unwrap();
+    let mut b = op.write_root().unwrap();
+    *a.elem() = 1;
+    *b.elem() = 2;
+}
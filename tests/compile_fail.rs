@@ -0,0 +1,9 @@
+//! Programs which must be rejected by the borrow checker, for bonzai to be sound.
+
+extern crate trybuild;
+
+#[test]
+fn borrow_guarantees() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/compile-fail/*.rs");
+}
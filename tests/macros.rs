@@ -0,0 +1,55 @@
+//! The exported macros, which can only be used from outside of bonzai.
+
+#[macro_use]
+extern crate bonzai;
+
+use bonzai::{Tree, ChildId};
+
+fn tree() -> Tree<i32, [ChildId; 2]> {
+    let mut tree = Tree::new();
+    {
+        let mut op = tree.operation();
+        op.put_root_elem(1);
+        op.write_root().unwrap().children().put_child_elem(1, 2).unwrap();
+    }
+    tree
+}
+
+#[test]
+fn traverse_from_guards() {
+    let mut tree = tree();
+    {
+        let mut op = tree.operation();
+        {
+            let child = op.read_root().unwrap().child(1).unwrap().unwrap();
+            let traverser = traverse_from!(op, child);
+            assert_eq!(*traverser, 2);
+        }
+        {
+            let child = op.read_root().unwrap().child(1).unwrap().unwrap();
+            let traverser = try_traverse_from!(op, child).unwrap();
+            traverser.seek_parent().unwrap();
+            assert_eq!(*traverser, 1);
+        }
+    }
+
+    let child = tree.read_root().unwrap().child(1).unwrap().unwrap();
+    assert_eq!(*traverse_read_from!(tree, child), 2);
+    let root = tree.read_root().unwrap();
+    assert_eq!(*try_traverse_read_from!(tree, root).unwrap(), 1);
+}
+
+#[test]
+fn get_elem_mut_from_guards() {
+    let mut tree = tree();
+    {
+        let child = tree.read_root().unwrap().child(1).unwrap().unwrap();
+        *get_elem_mut!(tree, child) = 3;
+    }
+    {
+        let mut op = tree.operation();
+        let child = op.read_root().unwrap().child(1).unwrap().unwrap();
+        *try_get_elem_mut!(op, child).unwrap() += 1;
+    }
+    assert_eq!(*tree.read_root().unwrap().child(1).unwrap().unwrap(), 4);
+}